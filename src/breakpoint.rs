@@ -0,0 +1,325 @@
+//! Breakpoints which pause the simulation when a signal element changes its value,
+//! or when a [condition](crate::expression) becomes true.
+//!
+//! Clients add breakpoints with [`Command::AddBreakpoint`](crate::to_simulator::Command::AddBreakpoint).
+//! When a breakpoint fires, the simulator pauses at the end of the delta cycle
+//! and sends [`SimulationUpdate::SimulationPaused`](crate::from_simulator::SimulationUpdate::SimulationPaused)
+//! with [`PauseReason::Breakpoint`](crate::from_simulator::PauseReason::Breakpoint).
+//!
+//! Independently, [`Command::BreakOnReport`](crate::to_simulator::Command::BreakOnReport) configures a [`ReportBreakpoint`]
+//! which pauses the simulation on assertion and report statements.
+
+use std::fmt;
+
+use compact_str::CompactString;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Logic;
+use crate::design_hierarchy::SignalElementId;
+use crate::design_hierarchy::SignalType;
+use crate::expression::Expression;
+use crate::from_simulator::CommandError;
+use crate::from_simulator::RawValue;
+use crate::from_simulator::Report;
+use crate::from_simulator::Severity;
+use crate::hierarchy_index::HierarchyIndex;
+use crate::signal_pattern::PatternError;
+use crate::value::Value;
+
+/// Simulator-assigned identifier of a [`Breakpoint`].
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Default,
+)]
+pub struct BreakpointId(pub u32);
+
+impl BreakpointId {
+    /// Returns the next identifier, wrapping around on overflow.
+    ///
+    /// After wrapping around, the identifier may still be in use; simulators assign IDs with [`Self::next_unused`].
+    #[must_use]
+    pub const fn next(self) -> Self {
+        Self(self.0.wrapping_add(1))
+    }
+
+    /// Returns the first identifier after this one for which `is_used` returns `false`,
+    /// wrapping around on overflow, or `None` if all identifiers are in use.
+    pub fn next_unused(self, mut is_used: impl FnMut(Self) -> bool) -> Option<Self> {
+        let mut id = self.next();
+        while is_used(id) {
+            if id == self {
+                return None;
+            }
+            id = id.next();
+        }
+        Some(id)
+    }
+}
+
+impl fmt::Display for BreakpointId {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "breakpoint {id}", id = self.0)
+    }
+}
+
+/// Value change of a signal element which fires a [`Breakpoint`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BreakCondition {
+    /// Any change of the value.
+    Change,
+    /// A change from `0` to `1`, like VHDL's `rising_edge`; only for `Bit` and `Logic` elements.
+    ///
+    /// For `Logic`, `L` counts as `0` and `H` as `1`.
+    Rising,
+    /// A change from `1` to `0`, like VHDL's `falling_edge`; only for `Bit` and `Logic` elements.
+    Falling,
+    /// A change to the given value.
+    Equals(RawValue),
+}
+
+impl BreakCondition {
+    /// Returns `true` if the change of an element from `previous` to `current` fulfills the condition.
+    ///
+    /// `typ` is the scalar type of the element.
+    pub fn fires(&self, typ: &SignalType, previous: RawValue, current: RawValue) -> bool {
+        if previous == current {
+            return false;
+        }
+        match *self {
+            BreakCondition::Change => true,
+            BreakCondition::Rising => {
+                level(typ, previous) == Some(false) && level(typ, current) == Some(true)
+            },
+            BreakCondition::Falling => {
+                level(typ, previous) == Some(true) && level(typ, current) == Some(false)
+            },
+            BreakCondition::Equals(value) => current == value,
+        }
+    }
+}
+
+/// Pauses the simulation when signal values change in a certain way.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Breakpoint {
+    /// Fires when a scalar signal element changes as described by the condition.
+    ValueChange {
+        element_id: SignalElementId,
+        condition: BreakCondition,
+    },
+    /// Fires in each delta cycle in which a signal of the expression changes
+    /// and the expression is true afterwards, like VHDL's `wait until`.
+    ///
+    /// Adapters evaluate the [checked](Expression::check) condition with [`Condition::fires`](crate::expression::Condition::fires).
+    Expression(Expression),
+}
+
+impl Breakpoint {
+    pub const fn value_change(element_id: SignalElementId, condition: BreakCondition) -> Self {
+        Breakpoint::ValueChange {
+            element_id,
+            condition,
+        }
+    }
+
+    /// Checks that the referenced signals exist and the condition applies to their types.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CommandError::UnknownSignals`] for an unknown element of a value change breakpoint,
+    /// and [`CommandError::InvalidBreakpoint`] for an edge condition on an element which is no `Bit` or `Logic`,
+    /// a value which is invalid for the element's type, or an expression which doesn't [check](Expression::check).
+    pub fn validate(&self, index: &HierarchyIndex<'_>) -> Result<(), CommandError> {
+        let (element_id, condition) = match self {
+            Breakpoint::ValueChange {
+                element_id,
+                condition,
+            } => (*element_id, *condition),
+            Breakpoint::Expression(expression) => {
+                return expression
+                    .check(index)
+                    .map(|_| ())
+                    .map_err(|error| CommandError::InvalidBreakpoint(error.to_string()));
+            },
+        };
+        let typ = index
+            .signal(element_id.signal_id)
+            .and_then(|signal| signal.typ.element_type(element_id.element_index))
+            .ok_or_else(|| CommandError::UnknownSignals(vec![element_id]))?;
+        match condition {
+            BreakCondition::Change => Ok(()),
+            BreakCondition::Rising | BreakCondition::Falling => match typ {
+                SignalType::Bit | SignalType::Logic => Ok(()),
+                _ => Err(CommandError::InvalidBreakpoint(
+                    "edge conditions require a bit or logic signal".into(),
+                )),
+            },
+            BreakCondition::Equals(value) => typ
+                .decode(value)
+                .map(|_| ())
+                .map_err(|error| CommandError::InvalidBreakpoint(error.to_string())),
+        }
+    }
+}
+
+/// Pauses the simulation after a report with at least the given severity,
+/// with [`PauseReason::Report`](crate::from_simulator::PauseReason::Report).
+///
+/// The regular expressions require the `regex` cargo feature and may match any part of the message or file name.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReportBreakpoint {
+    pub min_severity: Severity,
+    /// Only pause for reports whose message matches this regular expression.
+    pub message: Option<CompactString>,
+    /// Only pause for reports whose file name matches this regular expression.
+    pub file: Option<CompactString>,
+}
+
+impl ReportBreakpoint {
+    /// Pauses for all reports with at least the given severity.
+    pub const fn new(min_severity: Severity) -> Self {
+        Self {
+            min_severity,
+            message: None,
+            file: None,
+        }
+    }
+
+    /// Compiles the regular expressions for repeated matching.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a regex is invalid, or if one is given and the `regex` feature is disabled.
+    pub fn compile(&self) -> Result<ReportMatcher, PatternError> {
+        #[cfg(feature = "regex")]
+        let compile = |pattern: &Option<CompactString>| {
+            pattern
+                .as_deref()
+                .map(regex::Regex::new)
+                .transpose()
+                .map_err(|error| PatternError::InvalidRegex(error.to_string()))
+        };
+        #[cfg(not(feature = "regex"))]
+        if self.message.is_some() || self.file.is_some() {
+            return Err(PatternError::RegexUnsupported);
+        }
+        Ok(ReportMatcher {
+            min_severity: self.min_severity,
+            #[cfg(feature = "regex")]
+            message: compile(&self.message)?,
+            #[cfg(feature = "regex")]
+            file: compile(&self.file)?,
+        })
+    }
+}
+
+/// A compiled [`ReportBreakpoint`].
+#[derive(Debug, Clone)]
+pub struct ReportMatcher {
+    min_severity: Severity,
+    #[cfg(feature = "regex")]
+    message: Option<regex::Regex>,
+    #[cfg(feature = "regex")]
+    file: Option<regex::Regex>,
+}
+
+impl ReportMatcher {
+    /// Returns `true` if the report pauses the simulation.
+    pub fn is_match(&self, report: &Report) -> bool {
+        if report.severity < self.min_severity {
+            return false;
+        }
+        #[cfg(feature = "regex")]
+        {
+            let matches = |regex: &Option<regex::Regex>, text: &str| {
+                regex.as_ref().is_none_or(|regex| regex.is_match(text))
+            };
+            matches(&self.message, &report.message) && matches(&self.file, &report.file)
+        }
+        #[cfg(not(feature = "regex"))]
+        true
+    }
+}
+
+/// Returns the logic level of a `Bit` or `Logic` value, or `None` for other values.
+fn level(typ: &SignalType, raw: RawValue) -> Option<bool> {
+    match typ.decode(raw).ok()? {
+        Value::Bit(bit) => Some(bit),
+        Value::Logic(Logic::Zero | Logic::L) => Some(false),
+        Value::Logic(Logic::One | Logic::H) => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::design_hierarchy::Direction;
+    use crate::time::LogicalTime;
+
+    fn logic(value: Logic) -> RawValue {
+        RawValue::from(Value::Logic(value))
+    }
+
+    #[test]
+    fn fires_on_edges_and_values() {
+        let rising = BreakCondition::Rising;
+        assert!(rising.fires(&SignalType::Logic, logic(Logic::L), logic(Logic::One)));
+        assert!(!rising.fires(&SignalType::Logic, logic(Logic::X), logic(Logic::One)));
+        assert!(!rising.fires(&SignalType::Logic, logic(Logic::One), logic(Logic::H)));
+        assert!(BreakCondition::Falling.fires(&SignalType::Bit, RawValue(1), RawValue(0)));
+
+        let equals = BreakCondition::Equals(RawValue(5));
+        let integer = SignalType::Integer {
+            min: 0,
+            max: 9,
+            direction: Direction::To,
+        };
+        assert!(equals.fires(&integer, RawValue(4), RawValue(5)));
+        assert!(!equals.fires(&integer, RawValue(5), RawValue(5)));
+        assert!(BreakCondition::Change.fires(&integer, RawValue(5), RawValue(6)));
+    }
+
+    #[test]
+    fn skips_identifiers_in_use() {
+        let used = [BreakpointId(u32::MAX), BreakpointId(0), BreakpointId(2)];
+        assert_eq!(
+            BreakpointId(u32::MAX - 1).next_unused(|id| used.contains(&id)),
+            Some(BreakpointId(1))
+        );
+        assert_eq!(
+            BreakpointId(5).next_unused(|_| false),
+            Some(BreakpointId(6))
+        );
+    }
+
+    #[test]
+    fn matches_reports_by_severity() {
+        let report = |severity, message: &str| Report {
+            time: LogicalTime::ZERO,
+            message: message.into(),
+            severity,
+            file: "tb.vhd".into(),
+            line: 1,
+            column: 1,
+        };
+        let matcher = ReportBreakpoint::new(Severity::Error).compile().unwrap();
+        assert!(matcher.is_match(&report(Severity::Failure, "")));
+        assert!(!matcher.is_match(&report(Severity::Warning, "")));
+
+        let breakpoint = ReportBreakpoint {
+            message: Some("overflow|underflow".into()),
+            ..ReportBreakpoint::new(Severity::Warning)
+        };
+        #[cfg(feature = "regex")]
+        {
+            let matcher = breakpoint.compile().unwrap();
+            assert!(matcher.is_match(&report(Severity::Error, "fifo overflow")));
+            assert!(!matcher.is_match(&report(Severity::Error, "fifo full")));
+        }
+        #[cfg(not(feature = "regex"))]
+        assert_eq!(
+            breakpoint.compile().unwrap_err(),
+            PatternError::RegexUnsupported
+        );
+    }
+}
@@ -0,0 +1,750 @@
+//! Async WebSocket client for frontends like waveform viewers and debuggers,
+//! which connects to the WebSocket server of a simulator adapter.
+//! Available with the `client` cargo feature.
+//!
+//! [`Client::connect`] connects to the port advertised by a [`Marker`] and opens the session with a [`Hello`].
+//! A background task, which must run inside a Tokio runtime, owns the connection:
+//! when the connection drops, it resets the cached state, reconnects with exponential backoff
+//! and repeats the handshake.
+//! If the [`Welcome`] names the same simulation as before, it restores the subscribed signal elements;
+//! otherwise the subscriptions are dropped, since their element IDs belong to the previous simulation.
+//! Commands sent while disconnected or during the handshake are delivered after it.
+//!
+//! The requests of the background task use IDs with the highest bit set, which [`Client::send`] never returns.
+//! Successful results of these requests aren't forwarded to the [`Updates`]; failures are.
+//!
+//! Requests are sent as JSON text messages until the simulator confirms [`Feature::BinaryCodec`],
+//! and in the [binary encoding](crate::codec) afterwards. Both kinds of messages are accepted from the simulator.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Display;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use compact_str::CompactString;
+use futures_util::SinkExt;
+use futures_util::Stream;
+use futures_util::StreamExt;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+use tokio_tungstenite::MaybeTlsStream;
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::SimulationId;
+use crate::SimulationStatus;
+use crate::codec;
+use crate::codec::CodecError;
+use crate::design_hierarchy::DesignHierarchy;
+use crate::design_hierarchy::SignalElementId;
+use crate::discovery::discover;
+use crate::error::ErrorCode;
+use crate::error::SimulationError;
+use crate::from_simulator::CommandError;
+use crate::from_simulator::SimulationUpdate;
+use crate::handshake::Feature;
+use crate::handshake::Hello;
+use crate::handshake::Welcome;
+use crate::hierarchy_index::HierarchyIndex;
+use crate::server_marker::Marker;
+use crate::to_simulator::Command;
+use crate::to_simulator::ForceMode;
+use crate::to_simulator::Request;
+use crate::to_simulator::RequestId;
+use crate::value::Value;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Settings for [`Client::connect`].
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    /// Sent to the simulator in the [`Hello`].
+    pub client_name: Option<CompactString>,
+    /// Delay before the first reconnection attempt, doubled after every failed attempt.
+    pub reconnect_delay: Duration,
+    pub max_reconnect_delay: Duration,
+    /// Number of consecutive failed reconnection attempts after which the client gives up,
+    /// or `None` to retry forever.
+    pub max_reconnect_attempts: Option<u32>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            client_name: None,
+            reconnect_delay: Duration::from_millis(100),
+            max_reconnect_delay: Duration::from_secs(5),
+            max_reconnect_attempts: None,
+        }
+    }
+}
+
+/// Handle for sending commands to a simulator and querying the cached simulation state.
+///
+/// Clones share the same connection, which is closed when the last clone is dropped.
+#[derive(Debug, Clone)]
+pub struct Client {
+    marker: Marker,
+    commands: mpsc::UnboundedSender<Request>,
+    next_request_id: Arc<AtomicU32>,
+    welcome: watch::Receiver<Option<Welcome>>,
+    hierarchy: watch::Receiver<Option<Arc<DesignHierarchy>>>,
+    status: watch::Receiver<SimulationStatus>,
+}
+
+impl Client {
+    /// Connects to the simulator advertised by `marker`.
+    ///
+    /// Returns the client and the stream of updates received from the simulator.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the initial connection fails; later connection failures are retried.
+    pub async fn connect(
+        marker: Marker,
+        options: ClientOptions,
+    ) -> Result<(Self, Updates), ClientError> {
+        let url = format!("ws://127.0.0.1:{port}", port = marker.port);
+        let (socket, _) = tokio_tungstenite::connect_async(&url).await?;
+
+        let (commands, command_receiver) = mpsc::unbounded_channel();
+        let (update_sender, updates) = mpsc::unbounded_channel();
+        let (welcome_sender, welcome) = watch::channel(None);
+        let (hierarchy_sender, hierarchy) = watch::channel(None);
+        let (status_sender, status) = watch::channel(SimulationStatus::default());
+        let next_request_id = Arc::new(AtomicU32::new(0));
+
+        let connection = Connection {
+            url,
+            options,
+            commands: command_receiver,
+            updates: update_sender,
+            welcome: welcome_sender,
+            hierarchy: hierarchy_sender,
+            status: status_sender,
+            simulation_id: marker.simulation_id,
+            next_internal_id: 0,
+            subscriptions: BTreeSet::new(),
+            unsent: None,
+        };
+        tokio::spawn(connection.run(socket));
+
+        let client = Self {
+            marker,
+            commands,
+            next_request_id,
+            welcome,
+            hierarchy,
+            status,
+        };
+        Ok((client, Updates { receiver: updates }))
+    }
+
+    /// Connects to the simulation with the given ID, looking up its port with [`discover`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no marker for the simulation or the initial connection fails.
+    pub async fn connect_to_simulation(
+        simulation_id: SimulationId,
+        options: ClientOptions,
+    ) -> Result<(Self, Updates), ClientError> {
+        let marker = discover()
+            .into_iter()
+            .find(|marker| marker.simulation_id == simulation_id)
+            .ok_or(ClientError::SimulationNotFound(simulation_id))?;
+        Self::connect(marker, options).await
+    }
+
+    pub const fn marker(&self) -> Marker {
+        self.marker
+    }
+
+    /// Queues a command for sending and returns the ID of its request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection was given up.
+    pub fn send(&self, command: Command) -> Result<RequestId, ClientError> {
+        let id = next_request_id(&self.next_request_id);
+        self.commands
+            .send(Request::new(id, command))
+            .map_err(|_| ClientError::Closed)?;
+        Ok(id)
+    }
+
+    /// Sends a [`Command::Force`] after checking the value against the element's type.
+    ///
+    /// The index is built once from the [design hierarchy](Self::design_hierarchy) by the caller.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value is invalid or the connection was given up.
+    pub fn force(
+        &self,
+        index: &HierarchyIndex<'_>,
+        element: SignalElementId,
+        value: Value,
+        mode: ForceMode,
+    ) -> Result<RequestId, ClientError> {
+        let command =
+            Command::force(index, element, value, mode).map_err(ClientError::InvalidCommand)?;
+        self.send(command)
+    }
+
+    /// Sends a [`Command::Deposit`] after checking the value against the element's type.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::force`].
+    pub fn deposit(
+        &self,
+        index: &HierarchyIndex<'_>,
+        element: SignalElementId,
+        value: Value,
+    ) -> Result<RequestId, ClientError> {
+        let command =
+            Command::deposit(index, element, value).map_err(ClientError::InvalidCommand)?;
+        self.send(command)
+    }
+
+    /// Returns the simulator's reply to the handshake of the current connection,
+    /// or `None` while disconnected or waiting for the reply.
+    pub fn welcome(&self) -> Option<Welcome> {
+        self.welcome.borrow().clone()
+    }
+
+    /// Returns the latest design hierarchy received on the current connection.
+    pub fn design_hierarchy(&self) -> Option<Arc<DesignHierarchy>> {
+        self.hierarchy.borrow().clone()
+    }
+
+    /// Returns the simulation status according to the latest update received on the current connection,
+    /// or the default status before the first one.
+    pub fn status(&self) -> SimulationStatus {
+        *self.status.borrow()
+    }
+
+    /// Returns a receiver which is notified whenever the simulation status changes.
+    pub fn watch_status(&self) -> watch::Receiver<SimulationStatus> {
+        self.status.clone()
+    }
+
+    /// Returns `true` if the connection was given up after too many failed reconnection attempts.
+    pub fn is_closed(&self) -> bool {
+        self.commands.is_closed()
+    }
+}
+
+/// Stream of updates received by a [`Client`], ending when the connection is given up or closed.
+#[derive(Debug)]
+pub struct Updates {
+    receiver: mpsc::UnboundedReceiver<SimulationUpdate>,
+}
+
+impl Updates {
+    /// Receives the next update.
+    pub async fn recv(&mut self) -> Option<SimulationUpdate> {
+        self.receiver.recv().await
+    }
+}
+
+impl Stream for Updates {
+    type Item = SimulationUpdate;
+
+    fn poll_next(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(context)
+    }
+}
+
+/// Error when connecting to a simulator or sending a command.
+#[derive(Debug)]
+pub enum ClientError {
+    Io(io::Error),
+    WebSocket(Box<tungstenite::Error>),
+    Json(serde_json::Error),
+    Codec(CodecError),
+    /// There is no marker file for the simulation.
+    SimulationNotFound(SimulationId),
+    /// A command failed the check before sending.
+    InvalidCommand(CommandError),
+    /// The connection was given up.
+    Closed,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Io(error) => write!(formatter, "I/O error: {error}"),
+            ClientError::WebSocket(error) => write!(formatter, "WebSocket error: {error}"),
+            ClientError::Json(error) => write!(formatter, "JSON error: {error}"),
+            ClientError::Codec(error) => Display::fmt(error, formatter),
+            ClientError::SimulationNotFound(simulation_id) => {
+                write!(formatter, "no marker found for simulation {simulation_id}")
+            },
+            ClientError::InvalidCommand(error) => write!(formatter, "invalid command: {error}"),
+            ClientError::Closed => formatter.write_str("connection closed"),
+        }
+    }
+}
+
+impl Error for ClientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClientError::Io(error) => Some(error),
+            ClientError::WebSocket(error) => Some(error),
+            ClientError::Json(error) => Some(error),
+            ClientError::Codec(error) => Some(error),
+            ClientError::InvalidCommand(error) => Some(error),
+            ClientError::SimulationNotFound(_) | ClientError::Closed => None,
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(error: io::Error) -> Self {
+        ClientError::Io(error)
+    }
+}
+
+impl From<tungstenite::Error> for ClientError {
+    fn from(error: tungstenite::Error) -> Self {
+        ClientError::WebSocket(Box::new(error))
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(error: serde_json::Error) -> Self {
+        ClientError::Json(error)
+    }
+}
+
+impl From<CodecError> for ClientError {
+    fn from(error: CodecError) -> Self {
+        ClientError::Codec(error)
+    }
+}
+
+/// Bit set in the IDs of requests sent by the background task itself.
+const INTERNAL_REQUEST_BIT: u32 = 1 << 31;
+
+/// Returns the next ID for a request of the client's user, wrapping around below [`INTERNAL_REQUEST_BIT`].
+fn next_request_id(counter: &AtomicU32) -> RequestId {
+    RequestId(counter.fetch_add(1, Ordering::Relaxed) & !INTERNAL_REQUEST_BIT)
+}
+
+/// Returns `true` for a successful reply to a request of the background task.
+fn is_internal_success(update: &SimulationUpdate) -> bool {
+    let failed = matches!(
+        update,
+        SimulationUpdate::CommandResult { result: Err(_), .. } | SimulationUpdate::Error(_)
+    );
+    !failed
+        && update
+            .request_id()
+            .is_some_and(|request_id| request_id.0 & INTERNAL_REQUEST_BIT != 0)
+}
+
+/// Why a session ended.
+enum SessionEnd {
+    /// All clients were dropped.
+    Closed,
+    Disconnected,
+}
+
+/// State of the background task owning the WebSocket connection.
+struct Connection {
+    url: String,
+    options: ClientOptions,
+    commands: mpsc::UnboundedReceiver<Request>,
+    updates: mpsc::UnboundedSender<SimulationUpdate>,
+    welcome: watch::Sender<Option<Welcome>>,
+    hierarchy: watch::Sender<Option<Arc<DesignHierarchy>>>,
+    status: watch::Sender<SimulationStatus>,
+    /// The simulation of the latest [`Welcome`], which the subscriptions belong to.
+    simulation_id: SimulationId,
+    /// Sequence number of the next internal request, without [`INTERNAL_REQUEST_BIT`].
+    next_internal_id: u32,
+    /// Signal elements to subscribe again after reconnecting.
+    subscriptions: BTreeSet<SignalElementId>,
+    /// A request which couldn't be sent because the connection dropped.
+    unsent: Option<Request>,
+}
+
+impl Connection {
+    async fn run(mut self, mut socket: Socket) {
+        loop {
+            match self.session(socket).await {
+                SessionEnd::Closed => return,
+                SessionEnd::Disconnected => {
+                    // the simulation may have changed when reconnecting
+                    self.welcome.send_replace(None);
+                    self.hierarchy.send_replace(None);
+                    self.status.send_replace(SimulationStatus::default());
+                },
+            }
+            match self.reconnect().await {
+                Some(new_socket) => socket = new_socket,
+                None => return,
+            }
+        }
+    }
+
+    async fn reconnect(&self) -> Option<Socket> {
+        let mut delay = self.options.reconnect_delay;
+        let mut attempts = 0;
+        loop {
+            tokio::time::sleep(delay).await;
+            if self.commands.is_closed() {
+                return None;
+            }
+            if let Ok((socket, _)) = tokio_tungstenite::connect_async(&self.url).await {
+                return Some(socket);
+            }
+            attempts += 1;
+            if self
+                .options
+                .max_reconnect_attempts
+                .is_some_and(|max_attempts| attempts >= max_attempts)
+            {
+                return None;
+            }
+            delay = (delay * 2).min(self.options.max_reconnect_delay);
+        }
+    }
+
+    async fn session(&mut self, socket: Socket) -> SessionEnd {
+        let (mut sink, mut stream) = socket.split();
+        let mut binary = false;
+
+        let hello = Command::Hello(Hello::new(self.options.client_name.clone()));
+        let hello = self.internal_request(hello);
+        if send_request(&mut sink, &hello, binary).await.is_err() {
+            return SessionEnd::Disconnected;
+        }
+        // requests wait for the reply to the hello, which decides whether subscriptions are restored
+        let mut handshake = Some(hello.id);
+
+        loop {
+            tokio::select! {
+                request = self.commands.recv(), if handshake.is_none() => {
+                    let Some(request) = request else {
+                        let _ = sink.close().await;
+                        return SessionEnd::Closed;
+                    };
+                    if send_request(&mut sink, &request, binary).await.is_err() {
+                        self.unsent = Some(request);
+                        return SessionEnd::Disconnected;
+                    }
+                    self.track(&request.command);
+                },
+                message = stream.next() => {
+                    let update = match message {
+                        Some(Ok(Message::Text(text))) => serde_json::from_str(&text).map_err(ClientError::from),
+                        Some(Ok(Message::Binary(bytes))) => codec::decode(&bytes).map_err(ClientError::from),
+                        Some(Ok(Message::Close(_)) | Err(_)) | None => return SessionEnd::Disconnected,
+                        Some(Ok(_)) => continue,
+                    };
+                    let update = update.unwrap_or_else(|error| {
+                        SimulationError::new(ErrorCode::MalformedMessage, error.to_string()).into()
+                    });
+                    let completes_handshake = handshake.is_some()
+                        && (matches!(update, SimulationUpdate::Welcome(_))
+                            || update.request_id() == handshake);
+                    if let SimulationUpdate::Welcome(welcome) = &update {
+                        binary = welcome.supports(Feature::BinaryCodec);
+                        if welcome.simulation_id != self.simulation_id {
+                            self.subscriptions.clear();
+                            self.simulation_id = welcome.simulation_id;
+                        }
+                    }
+                    self.observe(&update);
+                    if !is_internal_success(&update) {
+                        // the client may not be interested in updates
+                        let _ = self.updates.send(update);
+                    }
+                    if completes_handshake {
+                        handshake = None;
+                        if self.restore(&mut sink, binary).await.is_err() {
+                            return SessionEnd::Disconnected;
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    /// Subscribes to the signal elements of the previous connection again
+    /// and sends the request which couldn't be sent on it.
+    async fn restore(
+        &mut self,
+        sink: &mut (impl SinkExt<Message, Error = tungstenite::Error> + Unpin),
+        binary: bool,
+    ) -> Result<(), ClientError> {
+        if !self.subscriptions.is_empty() {
+            let subscribe = Command::Subscribe(self.subscriptions.iter().copied().collect());
+            let subscribe = self.internal_request(subscribe);
+            send_request(sink, &subscribe, binary).await?;
+        }
+        if let Some(request) = self.unsent.take() {
+            if let Err(error) = send_request(sink, &request, binary).await {
+                self.unsent = Some(request);
+                return Err(error);
+            }
+            self.track(&request.command);
+        }
+        Ok(())
+    }
+
+    fn internal_request(&mut self, command: Command) -> Request {
+        let id = RequestId(self.next_internal_id | INTERNAL_REQUEST_BIT);
+        self.next_internal_id = self.next_internal_id.wrapping_add(1) & !INTERNAL_REQUEST_BIT;
+        Request::new(id, command)
+    }
+
+    /// Records subscription changes to restore them after reconnecting.
+    fn track(&mut self, command: &Command) {
+        match command {
+            Command::Subscribe(element_ids) => self.subscriptions.extend(element_ids),
+            Command::Unsubscribe(element_ids) => {
+                for element_id in element_ids {
+                    self.subscriptions.remove(element_id);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Updates the cached simulation state.
+    fn observe(&mut self, update: &SimulationUpdate) {
+        match update {
+            SimulationUpdate::Welcome(welcome) => {
+                self.welcome.send_replace(Some(welcome.clone()));
+            },
+            SimulationUpdate::SimulationStarted | SimulationUpdate::SimulationResumed => {
+                self.status.send_replace(SimulationStatus::Running);
+            },
+            SimulationUpdate::SimulationPaused { .. } => {
+                self.status.send_replace(SimulationStatus::Paused);
+            },
+            SimulationUpdate::SimulationStopped => {
+                self.status.send_replace(SimulationStatus::Stopped);
+            },
+            SimulationUpdate::DesignHierarchy(hierarchy) => {
+                self.hierarchy
+                    .send_replace(Some(Arc::new(hierarchy.clone())));
+            },
+            SimulationUpdate::PatternSubscribed { element_ids, .. } => {
+                self.subscriptions.extend(element_ids);
+            },
+            _ => {},
+        }
+    }
+}
+
+async fn send_request(
+    sink: &mut (impl SinkExt<Message, Error = tungstenite::Error> + Unpin),
+    request: &Request,
+    binary: bool,
+) -> Result<(), ClientError> {
+    let message = if binary {
+        Message::Binary(codec::encode(request)?.into())
+    } else {
+        Message::Text(serde_json::to_string(request)?.into())
+    };
+    sink.send(message).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    use super::*;
+    use crate::design_hierarchy::Direction;
+    use crate::design_hierarchy::SignalInstanceId;
+    use crate::design_hierarchy::SignalType;
+    use crate::from_simulator::RawValue;
+    use crate::handshake::ProtocolVersion;
+    use crate::test_utils::hierarchy;
+    use crate::test_utils::module;
+    use crate::test_utils::signal;
+    use crate::test_utils::signal_id;
+
+    async fn receive(server: &mut WebSocketStream<TcpStream>) -> (Request, bool) {
+        match server.next().await.unwrap().unwrap() {
+            Message::Text(text) => (serde_json::from_str(&text).unwrap(), false),
+            Message::Binary(bytes) => (codec::decode(&bytes).unwrap(), true),
+            message => panic!("unexpected message {message:?}"),
+        }
+    }
+
+    async fn send(server: &mut WebSocketStream<TcpStream>, update: &SimulationUpdate) {
+        let text = serde_json::to_string(update).unwrap();
+        server.send(Message::Text(text.into())).await.unwrap();
+    }
+
+    fn welcome(simulation_id: SimulationId, features: Vec<Feature>) -> SimulationUpdate {
+        SimulationUpdate::Welcome(Welcome {
+            simulation_id,
+            protocol_version: ProtocolVersion::CURRENT,
+            features,
+            simulator_name: "test".into(),
+            simulator_version: "1.0".into(),
+        })
+    }
+
+    /// Accepts the next connection of the client and receives its `Hello`.
+    async fn accept(listener: &TcpListener) -> (WebSocketStream<TcpStream>, Request) {
+        let mut server = accept_async(listener.accept().await.unwrap().0)
+            .await
+            .unwrap();
+        let (hello, binary) = receive(&mut server).await;
+        assert!(matches!(hello.command, Command::Hello(_)));
+        assert!(!binary);
+        (server, hello)
+    }
+
+    /// Connects a client to a new server and receives the client's `Hello`.
+    async fn connect() -> (Client, Updates, WebSocketStream<TcpStream>, TcpListener) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let marker = Marker {
+            port: listener.local_addr().unwrap().port(),
+            simulation_id: SimulationId::new_random(),
+        };
+        let (connected, (server, _)) = tokio::join!(
+            Client::connect(marker, ClientOptions::default()),
+            accept(&listener)
+        );
+        let (client, updates) = connected.unwrap();
+        (client, updates, server, listener)
+    }
+
+    #[tokio::test]
+    async fn checks_forced_and_deposited_values() {
+        let (client, _updates, mut server, _listener) = connect().await;
+        send(&mut server, &welcome(client.marker().simulation_id, vec![])).await;
+        let count = SignalType::Integer {
+            min: 0,
+            max: 15,
+            direction: Direction::To,
+        };
+        let hierarchy = hierarchy(vec![module("tb", vec![], vec![signal("count", 1, count)])]);
+        let index = HierarchyIndex::new(&hierarchy);
+        let element = SignalElementId::new_scalar(signal_id(1));
+
+        let request_id = client
+            .force(&index, element, Value::Integer(7), ForceMode::Driving)
+            .unwrap();
+        let (request, _) = receive(&mut server).await;
+        assert_eq!(request.id, request_id);
+        assert!(matches!(
+            request.command,
+            Command::Force {
+                value: RawValue(7),
+                mode: ForceMode::Driving,
+                ..
+            }
+        ));
+
+        assert!(matches!(
+            client.deposit(&index, element, Value::Integer(16)),
+            Err(ClientError::InvalidCommand(CommandError::InvalidValue(_)))
+        ));
+        client.deposit(&index, element, Value::Integer(0)).unwrap();
+        let (request, _) = receive(&mut server).await;
+        assert!(matches!(
+            request.command,
+            Command::Deposit {
+                value: RawValue(0),
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn reconnects_and_restores_subscriptions() {
+        let (client, mut updates, mut server, listener) = connect().await;
+        let simulation_id = client.marker().simulation_id;
+
+        for update in [
+            welcome(simulation_id, vec![Feature::BinaryCodec]),
+            SimulationUpdate::SimulationStarted,
+        ] {
+            send(&mut server, &update).await;
+        }
+        assert!(matches!(
+            updates.recv().await,
+            Some(SimulationUpdate::Welcome(_))
+        ));
+        assert!(matches!(
+            updates.recv().await,
+            Some(SimulationUpdate::SimulationStarted)
+        ));
+        assert_eq!(client.status(), SimulationStatus::Running);
+
+        let element_id = SignalElementId::new(SignalInstanceId(NonZeroU32::MIN), 3);
+        let request_id = client.send(Command::Subscribe(vec![element_id])).unwrap();
+        let (subscribe, binary) = receive(&mut server).await;
+        assert_eq!(subscribe.id, request_id);
+        assert!(binary);
+
+        drop(server);
+        let (mut server, hello) = accept(&listener).await;
+        assert!(client.welcome().is_none());
+        assert_eq!(client.status(), SimulationStatus::default());
+
+        // requests wait for the handshake, and the subscriptions are restored first
+        let pause_id = client.send(Command::PauseSimulation).unwrap();
+        send(&mut server, &welcome(simulation_id, vec![])).await;
+        let (subscribe, binary) = receive(&mut server).await;
+        assert!(matches!(
+            subscribe.command,
+            Command::Subscribe(element_ids) if element_ids == [element_id]
+        ));
+        assert!(!binary);
+        let (pause, _) = receive(&mut server).await;
+        assert_eq!(pause.id, pause_id);
+
+        // results of the internal requests don't reach the user, and their IDs don't collide
+        let mut ids = [hello.id, subscribe.id, pause_id, request_id];
+        ids.sort();
+        assert!(ids.windows(2).all(|pair| pair[0] != pair[1]));
+        for request_id in [hello.id, subscribe.id, pause_id] {
+            let result = SimulationUpdate::CommandResult {
+                request_id,
+                result: Ok(()),
+            };
+            send(&mut server, &result).await;
+        }
+        assert!(matches!(
+            updates.recv().await,
+            Some(SimulationUpdate::Welcome(_))
+        ));
+        assert!(matches!(
+            updates.recv().await,
+            Some(SimulationUpdate::CommandResult { request_id, .. }) if request_id == pause_id
+        ));
+
+        // the subscriptions of another simulation are dropped
+        drop(server);
+        let (mut server, _) = accept(&listener).await;
+        send(&mut server, &welcome(SimulationId::new_random(), vec![])).await;
+        let pause_id = client.send(Command::PauseSimulation).unwrap();
+        let (pause, _) = receive(&mut server).await;
+        assert_eq!(pause.id, pause_id);
+    }
+}
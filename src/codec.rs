@@ -0,0 +1,251 @@
+//! The official binary encoding of protocol messages, and length-prefixed framing for byte streams.
+//!
+//! Messages are encoded in the [postcard wire format](https://postcard.jamesmunns.com/wire-format),
+//! which is compact (variable-length integers) and not human-readable,
+//! so types using [`serde_utils`](crate::serde_utils) are encoded as raw integers instead of strings.
+//!
+//! A frame consists of the encoded message length as a little-endian `u32`, followed by the encoded message.
+//! Frames are only needed for byte streams like TCP or pipes;
+//! message-based transports like WebSocket binary messages carry an encoded message without length prefix.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Default upper limit for the length of a frame accepted by [`FrameReader`].
+pub const DEFAULT_MAX_FRAME_LENGTH: u32 = 256 * 1024 * 1024;
+
+/// Encodes a message without frame header.
+///
+/// # Errors
+///
+/// Returns an error if the message can't be serialized.
+pub fn encode<T: Serialize>(message: &T) -> Result<Vec<u8>, CodecError> {
+    postcard::to_stdvec(message).map_err(CodecError::Encoding)
+}
+
+/// Decodes a message without frame header.
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not a valid encoding of `T`, or if bytes are left over.
+pub fn decode<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T, CodecError> {
+    let (message, rest) = postcard::take_from_bytes(bytes).map_err(CodecError::Encoding)?;
+    if !rest.is_empty() {
+        return Err(CodecError::TrailingBytes(rest.len()));
+    }
+    Ok(message)
+}
+
+/// Writes length-prefixed messages to a byte stream.
+#[derive(Debug)]
+pub struct FrameWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> FrameWriter<W> {
+    pub const fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Writes and flushes a single frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message can't be encoded, is longer than `u32::MAX` bytes, or writing fails.
+    pub fn write_message<T: Serialize>(&mut self, message: &T) -> Result<(), CodecError> {
+        let payload = encode(message)?;
+        let length = u32::try_from(payload.len()).map_err(|_| CodecError::FrameTooLarge {
+            length: payload.len() as u64,
+            max_length: u32::MAX,
+        })?;
+        self.writer.write_all(&length.to_le_bytes())?;
+        self.writer.write_all(&payload)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads length-prefixed messages from a byte stream.
+#[derive(Debug)]
+pub struct FrameReader<R: Read> {
+    reader: R,
+    buffer: Vec<u8>,
+    max_frame_length: u32,
+}
+
+impl<R: Read> FrameReader<R> {
+    pub const fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: vec![],
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+        }
+    }
+
+    /// Sets the maximum accepted frame length, protecting against unbounded allocations.
+    #[must_use]
+    pub const fn with_max_frame_length(mut self, max_frame_length: u32) -> Self {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+
+    /// Reads a single frame, or returns `None` if the stream ended cleanly before a new frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails, the stream ends within a frame,
+    /// the frame is too long, or its payload is not a valid encoding of `T`.
+    pub fn read_message<T: DeserializeOwned>(&mut self) -> Result<Option<T>, CodecError> {
+        let mut header = [0; 4];
+        let mut filled = 0;
+        while filled < header.len() {
+            match self.reader.read(&mut header[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(count) => filled += count,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {},
+                Err(error) => return Err(error.into()),
+            }
+        }
+        let length = u32::from_le_bytes(header);
+        if length > self.max_frame_length {
+            return Err(CodecError::FrameTooLarge {
+                length: u64::from(length),
+                max_length: self.max_frame_length,
+            });
+        }
+
+        self.buffer.resize(length as usize, 0);
+        self.reader.read_exact(&mut self.buffer)?;
+        decode(&self.buffer).map(Some)
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Error when encoding, decoding or transferring a message.
+#[derive(Debug)]
+pub enum CodecError {
+    Io(io::Error),
+    Encoding(postcard::Error),
+    /// Bytes were left over after decoding a message.
+    TrailingBytes(usize),
+    FrameTooLarge {
+        length: u64,
+        max_length: u32,
+    },
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Io(error) => write!(formatter, "I/O error: {error}"),
+            CodecError::Encoding(error) => write!(formatter, "encoding error: {error}"),
+            CodecError::TrailingBytes(count) => {
+                write!(formatter, "{count} trailing bytes after message")
+            },
+            CodecError::FrameTooLarge { length, max_length } => write!(
+                formatter,
+                "frame length {length} exceeds maximum of {max_length} bytes",
+            ),
+        }
+    }
+}
+
+impl Error for CodecError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CodecError::Io(error) => Some(error),
+            CodecError::Encoding(error) => Some(error),
+            CodecError::TrailingBytes(_) | CodecError::FrameTooLarge { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for CodecError {
+    fn from(error: io::Error) -> Self {
+        CodecError::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::from_simulator::SimulationUpdate;
+    use crate::time::PhysicalTime;
+    use crate::to_simulator::Command;
+    use crate::to_simulator::Request;
+    use crate::to_simulator::RequestId;
+    use crate::to_simulator::RunUntil;
+
+    #[test]
+    fn encodes_times_as_integers() {
+        assert_eq!(encode(&PhysicalTime(300)).unwrap(), [0xac, 0x02]);
+        assert_eq!(
+            decode::<PhysicalTime>(&[0xac, 0x02]).unwrap(),
+            PhysicalTime(300)
+        );
+        assert!(matches!(
+            decode::<PhysicalTime>(&[0xac, 0x02, 0x00]),
+            Err(CodecError::TrailingBytes(1)),
+        ));
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut writer = FrameWriter::new(vec![]);
+        let request = Request::new(
+            RequestId(7),
+            Command::RunSimulation {
+                until: RunUntil::ForTime {
+                    duration: PhysicalTime(1_000_000),
+                },
+            },
+        );
+        writer.write_message(&request).unwrap();
+        writer
+            .write_message(&SimulationUpdate::SimulationStarted)
+            .unwrap();
+        let bytes = writer.into_inner();
+
+        let mut reader = FrameReader::new(bytes.as_slice());
+        let decoded: Request = reader.read_message().unwrap().unwrap();
+        assert_eq!(decoded.id, RequestId(7));
+        assert!(matches!(
+            decoded.command,
+            Command::RunSimulation {
+                until: RunUntil::ForTime {
+                    duration: PhysicalTime(1_000_000)
+                }
+            }
+        ));
+        let update: SimulationUpdate = reader.read_message().unwrap().unwrap();
+        assert!(matches!(update, SimulationUpdate::SimulationStarted));
+        assert!(reader.read_message::<SimulationUpdate>().unwrap().is_none());
+
+        let mut truncated = FrameReader::new(&bytes[..bytes.len() - 1]);
+        truncated.read_message::<Request>().unwrap();
+        assert!(truncated.read_message::<SimulationUpdate>().is_err());
+    }
+}
@@ -0,0 +1,425 @@
+//! Compact columnar representation of [`EventsUpdate`] for high event rates.
+//!
+//! Instead of one [`LogicalTime`] and one [`RawValue`] per event, each signal element stores
+//! a column of run-length encoded time steps and a column of values.
+//! Values are bit-packed if they fit into 1, 2, 4 or 8 bits, which covers all [`Bit`](crate::design_hierarchy::SignalType::Bit)
+//! and [`Logic`](crate::Logic) values, and stored as variable-length or fixed-width integers otherwise.
+//!
+//! The conversion from and to [`EventsUpdate`] is lossless, including the order of events.
+//!
+//! Time steps are stored relative to the previous event (or the start of the update's time range):
+//! the physical time difference, and either the delta cycle difference if the physical time is unchanged,
+//! or the absolute delta cycle otherwise. Runs of identical steps, like the edges of a clock, are stored once.
+
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::design_hierarchy::SignalElementId;
+use crate::from_simulator::Event;
+use crate::from_simulator::EventsUpdate;
+use crate::from_simulator::RawValue;
+use crate::from_simulator::Report;
+use crate::from_simulator::SignalEvents;
+use crate::time::Delta;
+use crate::time::LogicalTime;
+use crate::time::PhysicalTime;
+
+/// Columnar counterpart of [`EventsUpdate`].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ColumnarEventsUpdate {
+    pub time_range: Range<LogicalTime>,
+    pub signals: Vec<ColumnarSignalEvents>,
+    pub reports: Vec<Report>,
+}
+
+/// Columnar counterpart of [`SignalEvents`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ColumnarSignalEvents {
+    pub element_id: SignalElementId,
+    pub event_count: u32,
+    /// Runs of time steps, each encoded as the varints `run length`, `physical step` and `delta step`.
+    pub times: Vec<u8>,
+    pub values: ValueColumn,
+}
+
+/// Encoded values of a [`ColumnarSignalEvents`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum ValueColumn {
+    /// Values with `bits_per_value` bits each (1, 2, 4 or 8), packed starting at the least significant bit.
+    Packed { bits_per_value: u8, data: Vec<u8> },
+    /// LEB128 variable-length integers.
+    Varint(Vec<u8>),
+    /// Little-endian 64-bit integers, used when varints would be larger, e.g. for reals.
+    Fixed(Vec<u8>),
+}
+
+impl TryFrom<&EventsUpdate> for ColumnarEventsUpdate {
+    type Error = ColumnarError;
+
+    fn try_from(update: &EventsUpdate) -> Result<Self, Self::Error> {
+        Ok(Self {
+            time_range: update.time_range.clone(),
+            signals: update
+                .signals
+                .iter()
+                .map(|signal| ColumnarSignalEvents::encode(signal, update.time_range.start))
+                .collect::<Result<_, _>>()?,
+            reports: update.reports.clone(),
+        })
+    }
+}
+
+impl TryFrom<&ColumnarEventsUpdate> for EventsUpdate {
+    type Error = ColumnarError;
+
+    fn try_from(update: &ColumnarEventsUpdate) -> Result<Self, Self::Error> {
+        Ok(Self {
+            time_range: update.time_range.clone(),
+            signals: update
+                .signals
+                .iter()
+                .map(|signal| signal.decode(update.time_range.start))
+                .collect::<Result<_, _>>()?,
+            reports: update.reports.clone(),
+        })
+    }
+}
+
+impl ColumnarSignalEvents {
+    /// Encodes the events of one signal element, with time steps relative to `start`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ColumnarError::TooManyEvents`] if the element has more than `u32::MAX` events.
+    pub fn encode(signal: &SignalEvents, start: LogicalTime) -> Result<Self, ColumnarError> {
+        let event_count =
+            u32::try_from(signal.events.len()).map_err(|_| ColumnarError::TooManyEvents)?;
+        let mut times = vec![];
+        let mut previous = start;
+        let mut run: Option<((u64, u64), u64)> = None;
+        for event in &signal.events {
+            let step = time_step(previous, event.time);
+            previous = event.time;
+            run = match run {
+                Some((run_step, length)) if run_step == step => Some((step, length + 1)),
+                Some((run_step, length)) => {
+                    write_run(&mut times, run_step, length);
+                    Some((step, 1))
+                },
+                None => Some((step, 1)),
+            };
+        }
+        if let Some((step, length)) = run {
+            write_run(&mut times, step, length);
+        }
+
+        Ok(Self {
+            element_id: signal.element_id,
+            event_count,
+            times,
+            values: ValueColumn::encode(signal.events.iter().map(|event| event.value.0)),
+        })
+    }
+
+    /// Decodes the events, with time steps relative to `start`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the columns are malformed or don't contain [`Self::event_count`] events.
+    pub fn decode(&self, start: LogicalTime) -> Result<SignalEvents, ColumnarError> {
+        let count = self.event_count as usize;
+        let values = self.values.decode(count)?;
+
+        let mut events = Vec::with_capacity(values.len());
+        let mut position = 0;
+        let mut previous = start;
+        while position < self.times.len() {
+            let length = read_varint(&self.times, &mut position)?;
+            let step = (
+                read_varint(&self.times, &mut position)?,
+                read_varint(&self.times, &mut position)?,
+            );
+            for _ in 0..length {
+                let Some(&value) = values.get(events.len()) else {
+                    return Err(ColumnarError::CountMismatch);
+                };
+                previous = apply_time_step(previous, step);
+                events.push(Event {
+                    time: previous,
+                    value: RawValue(value),
+                });
+            }
+        }
+        if events.len() != count {
+            return Err(ColumnarError::CountMismatch);
+        }
+        Ok(SignalEvents {
+            element_id: self.element_id,
+            events,
+        })
+    }
+}
+
+impl ValueColumn {
+    fn encode(values: impl Iterator<Item = u64> + Clone) -> Self {
+        let max = values.clone().max().unwrap_or(0);
+        let bits_per_value = match max {
+            0..=1 => 1,
+            2..=3 => 2,
+            4..=15 => 4,
+            16..=255 => 8,
+            _ => {
+                let mut varints = vec![];
+                let mut count = 0;
+                for value in values.clone() {
+                    write_varint(&mut varints, value);
+                    count += 1;
+                }
+                if varints.len() <= count * 8 {
+                    return ValueColumn::Varint(varints);
+                }
+                return ValueColumn::Fixed(values.flat_map(u64::to_le_bytes).collect());
+            },
+        };
+
+        let values_per_byte = 8 / bits_per_value;
+        let mut data = vec![];
+        for (index, value) in values.enumerate() {
+            let shift = (index % values_per_byte) * bits_per_value;
+            if shift == 0 {
+                data.push(0);
+            }
+            if let Some(byte) = data.last_mut() {
+                *byte |= (value as u8) << shift;
+            }
+        }
+        ValueColumn::Packed {
+            bits_per_value: bits_per_value as u8,
+            data,
+        }
+    }
+
+    fn decode(&self, count: usize) -> Result<Vec<u64>, ColumnarError> {
+        // the count is untrusted, so don't allocate more than the data can hold
+        let (ValueColumn::Packed { data, .. }
+        | ValueColumn::Varint(data)
+        | ValueColumn::Fixed(data)) = self;
+        let mut values = Vec::with_capacity(count.min(data.len() * 8));
+        match self {
+            ValueColumn::Packed {
+                bits_per_value,
+                data,
+            } => {
+                let bits_per_value = usize::from(*bits_per_value);
+                if ![1, 2, 4, 8].contains(&bits_per_value) {
+                    return Err(ColumnarError::InvalidBitsPerValue(bits_per_value as u8));
+                }
+                let values_per_byte = 8 / bits_per_value;
+                let mask = u8::MAX >> (8 - bits_per_value);
+                if data.len() != count.div_ceil(values_per_byte) {
+                    return Err(ColumnarError::CountMismatch);
+                }
+                for index in 0..count {
+                    let shift = (index % values_per_byte) * bits_per_value;
+                    let byte = data[index / values_per_byte];
+                    values.push(u64::from((byte >> shift) & mask));
+                }
+            },
+            ValueColumn::Varint(data) => {
+                let mut position = 0;
+                while position < data.len() {
+                    values.push(read_varint(data, &mut position)?);
+                }
+            },
+            ValueColumn::Fixed(data) => {
+                let (chunks, rest) = data.as_chunks::<8>();
+                if !rest.is_empty() {
+                    return Err(ColumnarError::Truncated);
+                }
+                values.extend(chunks.iter().map(|chunk| u64::from_le_bytes(*chunk)));
+            },
+        }
+        if values.len() != count {
+            return Err(ColumnarError::CountMismatch);
+        }
+        Ok(values)
+    }
+}
+
+/// Returns the encoded step from `previous` to `time`; see the module documentation.
+fn time_step(previous: LogicalTime, time: LogicalTime) -> (u64, u64) {
+    let physical = time.physical.0.wrapping_sub(previous.physical.0);
+    let delta = if physical == 0 {
+        zigzag(time.delta.0.wrapping_sub(previous.delta.0))
+    } else {
+        time.delta.0
+    };
+    (zigzag(physical), delta)
+}
+
+fn apply_time_step(previous: LogicalTime, (physical, delta): (u64, u64)) -> LogicalTime {
+    let physical = unzigzag(physical);
+    if physical == 0 {
+        LogicalTime::new(
+            previous.physical,
+            Delta(previous.delta.0.wrapping_add(unzigzag(delta))),
+        )
+    } else {
+        LogicalTime::new(
+            PhysicalTime(previous.physical.0.wrapping_add(physical)),
+            Delta(delta),
+        )
+    }
+}
+
+/// Maps a two's complement difference to an unsigned integer with small values for small magnitudes.
+const fn zigzag(difference: u64) -> u64 {
+    let signed = difference as i64;
+    ((signed << 1) ^ (signed >> 63)) as u64
+}
+
+const fn unzigzag(value: u64) -> u64 {
+    (value >> 1) ^ (value & 1).wrapping_neg()
+}
+
+fn write_run(buffer: &mut Vec<u8>, (physical, delta): (u64, u64), length: u64) {
+    write_varint(buffer, length);
+    write_varint(buffer, physical);
+    write_varint(buffer, delta);
+}
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn read_varint(buffer: &[u8], position: &mut usize) -> Result<u64, ColumnarError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *buffer.get(*position).ok_or(ColumnarError::Truncated)?;
+        *position += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(ColumnarError::Truncated)
+}
+
+/// Error when encoding too many events or decoding a malformed [`ColumnarEventsUpdate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnarError {
+    /// A column ended within a value.
+    Truncated,
+    /// The columns don't contain the announced number of events.
+    CountMismatch,
+    InvalidBitsPerValue(u8),
+    /// A signal element has more events than [`ColumnarSignalEvents::event_count`] can hold.
+    TooManyEvents,
+}
+
+impl fmt::Display for ColumnarError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnarError::Truncated => formatter.write_str("column is truncated"),
+            ColumnarError::CountMismatch => {
+                formatter.write_str("columns don't match the event count")
+            },
+            ColumnarError::InvalidBitsPerValue(bits) => {
+                write!(formatter, "invalid number of bits per value: {bits}")
+            },
+            ColumnarError::TooManyEvents => {
+                formatter.write_str("too many events for the columnar representation")
+            },
+        }
+    }
+}
+
+impl Error for ColumnarError {}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::*;
+    use crate::design_hierarchy::SignalInstanceId;
+
+    fn signal(id: u32, events: &[((u64, u64), u64)]) -> SignalEvents {
+        SignalEvents {
+            element_id: SignalElementId::new_scalar(SignalInstanceId(NonZeroU32::new(id).unwrap())),
+            events: events
+                .iter()
+                .map(|&(time, value)| Event {
+                    time: time.into(),
+                    value: RawValue(value),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn round_trips_losslessly() {
+        let clock: Vec<_> = (0..1000).map(|i| ((i * 5_000, 1), 2 + i % 2)).collect();
+        let update = EventsUpdate {
+            time_range: LogicalTime::from(100)..LogicalTime::from(10_000_000),
+            signals: vec![
+                signal(1, &clock),
+                signal(2, &[((100, 3), 0.5f64.to_bits()), ((100, 2), u64::MAX)]),
+                signal(3, &[((7, 0), 300), ((7, 1), 1), ((u64::MAX, u64::MAX), 2)]),
+                signal(4, &[]),
+            ],
+            reports: vec![],
+        };
+
+        let columnar = ColumnarEventsUpdate::try_from(&update).unwrap();
+        assert!(matches!(
+            columnar.signals[0].values,
+            ValueColumn::Packed {
+                bits_per_value: 2,
+                ..
+            }
+        ));
+        assert!(matches!(columnar.signals[1].values, ValueColumn::Fixed(_)));
+        assert!(matches!(columnar.signals[2].values, ValueColumn::Varint(_)));
+        // the first step differs from the following clock edges
+        assert!(columnar.signals[0].times.len() < 10);
+
+        let decoded = EventsUpdate::try_from(&columnar).unwrap();
+        assert_eq!(decoded.time_range, update.time_range);
+        for (decoded, original) in decoded.signals.iter().zip(&update.signals) {
+            assert_eq!(decoded.element_id, original.element_id);
+            let times_and_values = |signal: &SignalEvents| {
+                signal
+                    .events
+                    .iter()
+                    .map(|event| (event.time, event.value))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(times_and_values(decoded), times_and_values(original));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_columns() {
+        let mut columnar =
+            ColumnarSignalEvents::encode(&signal(1, &[((1, 0), 1)]), LogicalTime::ZERO).unwrap();
+        columnar.event_count = 2;
+        assert_eq!(
+            columnar.decode(LogicalTime::ZERO).unwrap_err(),
+            ColumnarError::CountMismatch
+        );
+        columnar.event_count = 1;
+        columnar.times.pop();
+        assert_eq!(
+            columnar.decode(LogicalTime::ZERO).unwrap_err(),
+            ColumnarError::Truncated
+        );
+    }
+}
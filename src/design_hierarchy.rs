@@ -5,7 +5,15 @@ use compact_str::CompactString;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::Logic;
 use crate::SimulationId;
+use crate::element_path::ElementPath;
+use crate::element_path::PathSegment;
+use crate::from_simulator::RawValue;
+use crate::time::PhysicalTime;
+use crate::time::TimeUnit;
+use crate::value::Value;
+use crate::value::ValueError;
 
 /// Identifier for an instantiated signal in the design hierarchy.
 ///
@@ -65,10 +73,31 @@ pub struct DesignHierarchy {
     pub name: Option<CompactString>,
     /// The time the simulation was started, in seconds since the UNIX epoch.
     pub start_time: f64,
+    /// Granularity of all physical times in the simulation, e.g. 1 ps for GHDL's `--time-resolution=ps`.
+    ///
+    /// Defaults to 1 fs for simulators which don't send it.
+    #[serde(default = "default_time_resolution")]
+    pub time_resolution: PhysicalTime,
 
     pub root_modules: Vec<Module>,
 }
 
+impl DesignHierarchy {
+    /// Rounds a time, e.g. a cursor position, to the nearest multiple of the [time resolution](Self::time_resolution).
+    pub const fn snap_time(&self, time: PhysicalTime) -> PhysicalTime {
+        time.round_to(self.time_resolution)
+    }
+
+    /// Returns the number of fraction digits which are meaningful when displaying times in `unit`.
+    pub fn fraction_digits(&self, unit: TimeUnit) -> usize {
+        self.time_resolution.fraction_digits(unit)
+    }
+}
+
+const fn default_time_resolution() -> PhysicalTime {
+    PhysicalTime(1)
+}
+
 /// Either a design entity or a package.
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Module {
@@ -97,6 +126,20 @@ pub struct Signal {
     pub typ: SignalType,
 }
 
+impl Signal {
+    /// Returns the name of a scalar element, like `bus(3).payload.valid`.
+    pub fn element_name(&self, element_index: u32) -> Option<String> {
+        let path = self.typ.element_path(element_index)?;
+        Some(format!("{name}{path}", name = self.name))
+    }
+
+    /// Returns the element index for an element name as returned by [`Self::element_name`].
+    pub fn element_index_by_name(&self, element_name: &str) -> Option<u32> {
+        let path = element_name.strip_prefix(self.name.as_str())?;
+        self.typ.element_index(&path.parse().ok()?)
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum SignalType {
     Bit,
@@ -139,6 +182,179 @@ impl SignalType {
             _ => 1,
         }
     }
+
+    /// Returns the path to the scalar element with the given index,
+    /// or `None` if the index is out of range.
+    pub fn element_path(&self, element_index: u32) -> Option<ElementPath> {
+        let mut segments = vec![];
+        let mut typ = self;
+        let mut element_index = element_index;
+        loop {
+            if element_index >= typ.element_count() {
+                return None;
+            }
+            match typ {
+                SignalType::Array {
+                    left,
+                    right,
+                    direction,
+                    element_type,
+                    ..
+                } => {
+                    let stride = element_type.element_count();
+                    let position = element_index.checked_div(stride)?;
+                    if position >= direction.length_for(*left, *right) {
+                        return None;
+                    }
+                    segments.push(PathSegment::Index(direction.index_at(*left, position)));
+                    element_index %= stride;
+                    typ = element_type;
+                },
+                SignalType::Record { fields, .. } => {
+                    let (field, index) = find_field(fields, element_index)?;
+                    segments.push(PathSegment::Field(field.name.clone()));
+                    element_index = index;
+                    typ = &field.typ;
+                },
+                _ => return Some(ElementPath(segments)),
+            }
+        }
+    }
+
+    /// Returns the index of the scalar element at the given path,
+    /// or `None` if the path doesn't lead to a scalar element.
+    pub fn element_index(&self, path: &ElementPath) -> Option<u32> {
+        let mut element_index = 0u32;
+        let mut typ = self;
+        for segment in &path.0 {
+            match (typ, segment) {
+                (
+                    SignalType::Array {
+                        left,
+                        right,
+                        direction,
+                        element_type,
+                        ..
+                    },
+                    &PathSegment::Index(index),
+                ) => {
+                    let position = direction.position_of(*left, *right, index)?;
+                    element_index = position
+                        .checked_mul(element_type.element_count())
+                        .and_then(|offset| element_index.checked_add(offset))?;
+                    typ = element_type;
+                },
+                (SignalType::Record { fields, .. }, PathSegment::Field(name)) => {
+                    let field = fields.iter().find(|field| field.name == *name)?;
+                    element_index = element_index.checked_add(field.element_offset)?;
+                    typ = &field.typ;
+                },
+                _ => return None,
+            }
+        }
+        match typ {
+            SignalType::Array { .. } | SignalType::Record { .. } => None,
+            _ => Some(element_index),
+        }
+    }
+
+    /// Returns the scalar type of the element with the given index,
+    /// or `None` if the index is out of range.
+    pub fn element_type(&self, element_index: u32) -> Option<&SignalType> {
+        let mut typ = self;
+        let mut element_index = element_index;
+        loop {
+            if element_index >= typ.element_count() {
+                return None;
+            }
+            match typ {
+                SignalType::Array { element_type, .. } => {
+                    element_index = element_index.checked_rem(element_type.element_count())?;
+                    typ = element_type;
+                },
+                SignalType::Record { fields, .. } => {
+                    let (field, index) = find_field(fields, element_index)?;
+                    element_index = index;
+                    typ = &field.typ;
+                },
+                _ => return Some(typ),
+            }
+        }
+    }
+
+    /// Decodes a raw value of a scalar signal of this type, checking it against the type's range.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this type is not scalar, or if the raw value is no valid value of this type.
+    pub fn decode(&self, raw: RawValue) -> Result<Value, ValueError> {
+        let value = match *self {
+            SignalType::Bit => match raw.0 {
+                0 => Value::Bit(false),
+                1 => Value::Bit(true),
+                _ => return Err(ValueError::InvalidEncoding(raw)),
+            },
+            SignalType::Logic => u8::try_from(raw.0)
+                .ok()
+                .and_then(|discriminant| Logic::try_from(discriminant).ok())
+                .map(Value::Logic)
+                .ok_or(ValueError::InvalidEncoding(raw))?,
+            SignalType::Integer { .. } => Value::Integer(raw.0 as i64),
+            SignalType::Real { .. } => Value::Real(f64::from_bits(raw.0)),
+            SignalType::Enumeration { .. } => Value::Enumeration(
+                u32::try_from(raw.0).map_err(|_| ValueError::InvalidEncoding(raw))?,
+            ),
+            SignalType::Array { .. } | SignalType::Record { .. } => {
+                return Err(ValueError::NotScalar);
+            },
+            SignalType::Unsupported => return Err(ValueError::UnsupportedType),
+        };
+        self.check(value)?;
+        Ok(value)
+    }
+
+    /// Encodes a value of a scalar signal of this type, checking it against the type's range.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this type is not scalar, or if the value is no valid value of this type.
+    pub fn encode(&self, value: Value) -> Result<RawValue, ValueError> {
+        self.check(value)?;
+        Ok(RawValue::from(value))
+    }
+
+    fn check(&self, value: Value) -> Result<(), ValueError> {
+        match (self, value) {
+            (SignalType::Bit, Value::Bit(_)) | (SignalType::Logic, Value::Logic(_)) => Ok(()),
+            (&SignalType::Integer { min, max, .. }, Value::Integer(value)) => {
+                if (min..=max).contains(&value) {
+                    Ok(())
+                } else {
+                    Err(ValueError::IntegerOutOfRange { value, min, max })
+                }
+            },
+            (&SignalType::Real { min, max, .. }, Value::Real(value)) => {
+                if (min..=max).contains(&value) {
+                    Ok(())
+                } else {
+                    Err(ValueError::RealOutOfRange { value, min, max })
+                }
+            },
+            (SignalType::Enumeration { names }, Value::Enumeration(index)) => {
+                if (index as usize) < names.len() {
+                    Ok(())
+                } else {
+                    Err(ValueError::EnumerationIndexOutOfRange {
+                        index,
+                        count: names.len(),
+                    })
+                }
+            },
+            (SignalType::Array { .. } | SignalType::Record { .. }, _) => Err(ValueError::NotScalar),
+            (SignalType::Unsupported, _) => Err(ValueError::UnsupportedType),
+            (_, value) => Err(ValueError::TypeMismatch(value)),
+        }
+    }
 }
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct RecordField {
@@ -148,6 +364,16 @@ pub struct RecordField {
     pub element_offset: u32,
 }
 
+/// Returns the field containing a record's element, and the element's index within the field.
+fn find_field(fields: &[RecordField], element_index: u32) -> Option<(&RecordField, u32)> {
+    fields.iter().find_map(|field| {
+        let index = element_index
+            .checked_sub(field.element_offset)
+            .filter(|&index| index < field.typ.element_count())?;
+        Some((field, index))
+    })
+}
+
 #[derive(Clone, Copy, Serialize, Deserialize, Debug)]
 pub enum Direction {
     To,
@@ -155,22 +381,34 @@ pub enum Direction {
 }
 
 impl Direction {
+    /// Returns the number of indices in the range, saturating at `u32::MAX`.
     pub fn length_for(&self, left: i32, right: i32) -> u32 {
-        match self {
-            Direction::To => {
-                if right >= left {
-                    (right - left + 1) as u32
-                } else {
-                    0
-                }
-            },
-            Direction::Downto => {
-                if left >= right {
-                    (left - right + 1) as u32
-                } else {
-                    0
-                }
-            },
-        }
+        let (low, high) = match self {
+            Direction::To => (left, right),
+            Direction::Downto => (right, left),
+        };
+        let length = (i64::from(high) - i64::from(low) + 1).max(0);
+        u32::try_from(length).unwrap_or(u32::MAX)
+    }
+
+    /// Returns the array index at the zero-based `position`, counted from the `left` bound.
+    pub fn index_at(&self, left: i32, position: u32) -> i32 {
+        let index = match self {
+            Direction::To => i64::from(left) + i64::from(position),
+            Direction::Downto => i64::from(left) - i64::from(position),
+        };
+        index as i32
+    }
+
+    /// Returns the zero-based position of `index` counted from the `left` bound,
+    /// or `None` if `index` lies outside of the range.
+    pub fn position_of(&self, left: i32, right: i32, index: i32) -> Option<u32> {
+        let position = match self {
+            Direction::To => i64::from(index) - i64::from(left),
+            Direction::Downto => i64::from(left) - i64::from(index),
+        };
+        u32::try_from(position)
+            .ok()
+            .filter(|&position| position < self.length_for(left, right))
     }
 }
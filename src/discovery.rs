@@ -0,0 +1,293 @@
+//! Listing and watching running simulations through their [marker files](crate::server_marker).
+//!
+//! With the `watch` cargo feature, [`MarkerWatcher`] is notified about changes in the markers directory
+//! by the operating system (inotify on Linux).
+//! Without it, or if the directory can't be watched, it rescans the directory periodically.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use crate::server_marker::Marker;
+use crate::server_marker::MarkerMetadata;
+use crate::server_marker::create_markers_directory;
+#[cfg(doc)]
+use crate::server_marker::is_stale;
+use crate::server_marker::markers_directory;
+#[cfg(doc)]
+use crate::server_marker::remove_stale_markers;
+
+/// Interval between rescans of the markers directory.
+///
+/// When the directory is watched by the operating system, rescans only catch missed notifications.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Returns all markers in the [markers directory](markers_directory),
+/// ordered by the modification time of their files (oldest first).
+///
+/// Markers of simulations which exited without removing them are included;
+/// check them with [`is_stale`] or remove them first with [`remove_stale_markers`].
+/// Unreadable entries are skipped; a missing markers directory yields an empty list.
+pub fn discover() -> Vec<Marker> {
+    discover_in([markers_directory()])
+        .into_iter()
+        .map(|file| file.marker)
+        .collect()
+}
+
+/// Same as [`discover`], but enumerates the marker files in all given directories,
+/// e.g. the [shared](crate::server_marker::shared_markers_directory)
+/// and the [per-user](crate::server_marker::user_markers_directory) directory.
+pub fn discover_in<P: AsRef<Path>>(directories: impl IntoIterator<Item = P>) -> Vec<MarkerFile> {
+    let mut files: Vec<(Option<SystemTime>, MarkerFile)> = vec![];
+    for directory in directories {
+        let Ok(entries) = fs::read_dir(directory) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            let Some(marker) = Marker::try_from_path(&path) else {
+                continue;
+            };
+            let modified = entry.metadata().and_then(|metadata| metadata.modified());
+            files.push((modified.ok(), MarkerFile { marker, path }));
+        }
+    }
+    files.sort_by_key(|(modified, _)| *modified);
+    files.into_iter().map(|(_, file)| file).collect()
+}
+
+/// A marker found by [`discover_in`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkerFile {
+    pub marker: Marker,
+    pub path: PathBuf,
+}
+
+impl MarkerFile {
+    /// Reads the metadata from the marker file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the marker file can't be read.
+    pub fn metadata(&self) -> io::Result<MarkerMetadata> {
+        MarkerMetadata::read(&self.path)
+    }
+}
+
+/// Change in the set of running simulations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscoveryEvent {
+    Added(Marker),
+    Removed(Marker),
+}
+
+/// Reports simulations appearing and disappearing in the markers directory.
+///
+/// A background thread compares the [discovered](discover) markers with the previous scan;
+/// markers present at creation are reported as [added](DiscoveryEvent::Added) first.
+/// The thread stops when the watcher is dropped.
+#[derive(Debug)]
+pub struct MarkerWatcher {
+    events: mpsc::Receiver<DiscoveryEvent>,
+    _wake: mpsc::Sender<()>,
+    #[cfg(feature = "watch")]
+    _watcher: Option<notify::RecommendedWatcher>,
+}
+
+impl MarkerWatcher {
+    /// Creates a watcher which is notified by the operating system if possible,
+    /// and rescans every [`DEFAULT_POLL_INTERVAL`] otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the markers directory doesn't exist and can't be created,
+    /// or the background thread can't be spawned.
+    pub fn new() -> io::Result<Self> {
+        Self::new_in(create_markers_directory()?)
+    }
+
+    /// Same as [`new`](Self::new), but watches `directory` instead of the markers directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the background thread can't be spawned.
+    pub fn new_in(directory: impl Into<PathBuf>) -> io::Result<Self> {
+        let directory = directory.into();
+        let (wake, wake_receiver) = mpsc::channel();
+
+        #[cfg(feature = "watch")]
+        let watcher = {
+            use notify::Watcher;
+
+            let notify = wake.clone();
+            notify::recommended_watcher(move |_| {
+                // the scanning thread only stops after the watcher is dropped
+                let _ = notify.send(());
+            })
+            .and_then(|mut watcher| {
+                watcher.watch(&directory, notify::RecursiveMode::NonRecursive)?;
+                Ok(watcher)
+            })
+            .ok()
+        };
+
+        let events = spawn_scanner(directory, wake_receiver, DEFAULT_POLL_INTERVAL)?;
+        Ok(Self {
+            events,
+            _wake: wake,
+            #[cfg(feature = "watch")]
+            _watcher: watcher,
+        })
+    }
+
+    /// Creates a watcher which only rescans the markers directory every `interval`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the background thread can't be spawned.
+    pub fn polling(interval: Duration) -> io::Result<Self> {
+        Self::polling_in(markers_directory(), interval)
+    }
+
+    /// Same as [`polling`](Self::polling), but rescans `directory` instead of the markers directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the background thread can't be spawned.
+    pub fn polling_in(directory: impl Into<PathBuf>, interval: Duration) -> io::Result<Self> {
+        let (wake, wake_receiver) = mpsc::channel();
+        let events = spawn_scanner(directory.into(), wake_receiver, interval)?;
+        Ok(Self {
+            events,
+            _wake: wake,
+            #[cfg(feature = "watch")]
+            _watcher: None,
+        })
+    }
+
+    /// Waits for the next event.
+    ///
+    /// Returns `None` if the background thread stopped unexpectedly.
+    pub fn recv(&self) -> Option<DiscoveryEvent> {
+        self.events.recv().ok()
+    }
+
+    /// Waits at most `timeout` for the next event.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<DiscoveryEvent> {
+        self.events.recv_timeout(timeout).ok()
+    }
+
+    /// Returns the next event if one is pending.
+    pub fn try_recv(&self) -> Option<DiscoveryEvent> {
+        self.events.try_recv().ok()
+    }
+}
+
+/// Spawns the thread which rescans `directory` whenever it is woken or `interval` passed,
+/// until all wake senders are dropped.
+fn spawn_scanner(
+    directory: PathBuf,
+    wake: mpsc::Receiver<()>,
+    interval: Duration,
+) -> io::Result<mpsc::Receiver<DiscoveryEvent>> {
+    let (events, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("marker-watcher".into())
+        .spawn(move || {
+            let mut known: HashSet<Marker> = HashSet::new();
+            loop {
+                let markers: Vec<Marker> = discover_in([&directory])
+                    .into_iter()
+                    .map(|file| file.marker)
+                    .collect();
+                let current: HashSet<Marker> = markers.iter().copied().collect();
+                let removed = known
+                    .difference(&current)
+                    .copied()
+                    .map(DiscoveryEvent::Removed);
+                let added = markers
+                    .iter()
+                    .filter(|marker| !known.contains(marker))
+                    .copied()
+                    .map(DiscoveryEvent::Added);
+                for event in removed.chain(added) {
+                    if events.send(event).is_err() {
+                        return;
+                    }
+                }
+                known = current;
+
+                match wake.recv_timeout(interval) {
+                    Ok(()) | Err(mpsc::RecvTimeoutError::Timeout) => {},
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+                // coalesce bursts of notifications into a single scan
+                while wake.try_recv().is_ok() {}
+            }
+        })?;
+    Ok(receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimulationId;
+    use crate::server_marker::MarkerGuard;
+    use crate::test_utils::temp_directory;
+
+    #[test]
+    fn discovers_markers_in_multiple_directories() {
+        let root =
+            std::env::temp_dir().join(format!("hdl-sim-test-{}", SimulationId::new_random()));
+        let directories = [root.join("a"), root.join("b")];
+        let mut expected = vec![];
+        for (port, directory) in (1..).zip(&directories) {
+            fs::create_dir_all(directory).unwrap();
+            let marker = Marker {
+                port,
+                simulation_id: SimulationId::new_random(),
+            };
+            let path = directory.join(format!("{port}-{}.server", marker.simulation_id));
+            fs::write(&path, "user=alice\n").unwrap();
+            fs::write(directory.join("unrelated.txt"), "").unwrap();
+            expected.push(MarkerFile { marker, path });
+        }
+
+        let mut files = discover_in(&directories);
+        files.sort_by_key(|file| file.marker.port);
+        assert_eq!(files, expected);
+        assert_eq!(files[0].metadata().unwrap().user.as_deref(), Some("alice"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn watcher_reports_added_and_removed_markers() {
+        let directory = temp_directory();
+        let timeout = Duration::from_secs(5);
+        let watcher = MarkerWatcher::polling_in(&directory, Duration::from_millis(10)).unwrap();
+        let marker = Marker {
+            port: 1,
+            simulation_id: SimulationId::new_random(),
+        };
+        let guard = MarkerGuard::create_in(&directory, marker, &MarkerMetadata::default()).unwrap();
+        assert_eq!(
+            watcher.recv_timeout(timeout),
+            Some(DiscoveryEvent::Added(marker))
+        );
+
+        drop(guard);
+        assert_eq!(
+            watcher.recv_timeout(timeout),
+            Some(DiscoveryEvent::Removed(marker))
+        );
+        assert_eq!(watcher.try_recv(), None);
+        fs::remove_dir(directory).unwrap();
+    }
+}
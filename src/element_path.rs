@@ -0,0 +1,216 @@
+//! Paths to scalar elements within array and record signals, like `(3).payload.valid`.
+//!
+//! An [`ElementPath`] is the human-readable counterpart of
+//! [`SignalElementId::element_index`](crate::design_hierarchy::SignalElementId::element_index).
+//! Use [`SignalType::element_path`] and [`SignalType::element_index`] to convert between both.
+
+use std::fmt;
+use std::str::FromStr;
+
+use compact_str::CompactString;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[cfg(doc)]
+use crate::design_hierarchy::SignalType;
+
+/// One step into an aggregate signal type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PathSegment {
+    /// An array index in the array's own index range, not a zero-based position.
+    Index(i32),
+    /// A record field name.
+    Field(CompactString),
+}
+
+/// Path from a signal to one of its scalar elements.
+///
+/// The path of a scalar signal is empty.
+/// The textual form appends array indices in parentheses and record fields with a leading dot,
+/// e.g. `(3).payload.valid`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ElementPath(pub Vec<PathSegment>);
+
+impl ElementPath {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Display for ElementPath {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in &self.0 {
+            match segment {
+                PathSegment::Index(index) => write!(formatter, "({index})")?,
+                PathSegment::Field(name) => write!(formatter, ".{name}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ElementPath {
+    type Err = String;
+
+    /// Parses a path like `(3).payload.valid`.
+    /// Multi-dimensional indices like `(1, 2)` are accepted as a shorthand for `(1)(2)`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut segments = vec![];
+        let mut rest = value.trim();
+        while !rest.is_empty() {
+            if let Some(after_paren) = rest.strip_prefix('(') {
+                let (indices, after) = after_paren
+                    .split_once(')')
+                    .ok_or_else(|| format!("missing ')' in element path {value:?}"))?;
+                for index in indices.split(',') {
+                    let index = index
+                        .trim()
+                        .parse()
+                        .map_err(|error| format!("invalid index {index:?}: {error}"))?;
+                    segments.push(PathSegment::Index(index));
+                }
+                rest = after.trim_start();
+            } else if let Some(after_dot) = rest.strip_prefix('.') {
+                let end = after_dot.find(['.', '(']).unwrap_or(after_dot.len());
+                let (name, after) = after_dot.split_at(end);
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err(format!("empty field name in element path {value:?}"));
+                }
+                segments.push(PathSegment::Field(name.into()));
+                rest = after;
+            } else {
+                return Err(format!(
+                    "expected '(' or '.' in element path {value:?} at {rest:?}"
+                ));
+            }
+        }
+        Ok(Self(segments))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::design_hierarchy::Direction;
+    use crate::design_hierarchy::RecordField;
+    use crate::design_hierarchy::SignalType;
+
+    /// `array (7 downto 4) of record payload: record valid, data: std_logic end; last: bit; end`
+    fn bus_type() -> SignalType {
+        let payload = SignalType::Record {
+            fields: vec![
+                RecordField {
+                    name: "valid".into(),
+                    typ: SignalType::Logic,
+                    element_offset: 0,
+                },
+                RecordField {
+                    name: "data".into(),
+                    typ: SignalType::Logic,
+                    element_offset: 1,
+                },
+            ],
+            element_count: 2,
+        };
+        let element = SignalType::Record {
+            fields: vec![
+                RecordField {
+                    name: "payload".into(),
+                    typ: payload,
+                    element_offset: 0,
+                },
+                RecordField {
+                    name: "last".into(),
+                    typ: SignalType::Bit,
+                    element_offset: 2,
+                },
+            ],
+            element_count: 3,
+        };
+        SignalType::Array {
+            left: 7,
+            right: 4,
+            direction: Direction::Downto,
+            element_count: 12,
+            element_type: Box::new(element),
+        }
+    }
+
+    #[test]
+    fn resolves_paths_in_both_directions() {
+        let typ = bus_type();
+        for element_index in 0..typ.element_count() {
+            let path = typ.element_path(element_index).unwrap();
+            assert_eq!(typ.element_index(&path), Some(element_index));
+            assert_eq!(path.to_string().parse(), Ok(path));
+        }
+        assert_eq!(typ.element_path(4).unwrap().to_string(), "(6).payload.data");
+        assert!(typ.element_path(12).is_none());
+        assert!(typ.element_index(&"(3).last".parse().unwrap()).is_none());
+        assert!(typ.element_index(&"(5).payload".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_types() {
+        let empty_elements = SignalType::Array {
+            left: 0,
+            right: 3,
+            direction: Direction::To,
+            element_count: 4,
+            element_type: Box::new(SignalType::Record {
+                fields: vec![],
+                element_count: 0,
+            }),
+        };
+        assert!(empty_elements.element_path(1).is_none());
+        assert!(empty_elements.element_type(1).is_none());
+
+        let huge_offset = SignalType::Record {
+            fields: vec![RecordField {
+                name: "x".into(),
+                typ: SignalType::Array {
+                    left: 0,
+                    right: 1,
+                    direction: Direction::To,
+                    element_count: u32::MAX,
+                    element_type: Box::new(SignalType::Array {
+                        left: 0,
+                        right: i32::MAX,
+                        direction: Direction::To,
+                        element_count: u32::MAX,
+                        element_type: Box::new(SignalType::Bit),
+                    }),
+                },
+                element_offset: u32::MAX,
+            }],
+            element_count: u32::MAX,
+        };
+        assert!(huge_offset.element_path(u32::MAX - 1).is_none());
+        assert!(huge_offset.element_type(u32::MAX - 1).is_none());
+        assert!(
+            huge_offset
+                .element_index(&".x(1)(0)".parse().unwrap())
+                .is_none()
+        );
+        assert!(
+            huge_offset
+                .element_index(&".x(0)(1)".parse().unwrap())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn parses_multi_dimensional_indices() {
+        assert_eq!(
+            "(1, -2).x".parse(),
+            Ok(ElementPath(vec![
+                PathSegment::Index(1),
+                PathSegment::Index(-2),
+                PathSegment::Field("x".into()),
+            ])),
+        );
+        assert!("(1".parse::<ElementPath>().is_err());
+        assert!("x".parse::<ElementPath>().is_err());
+    }
+}
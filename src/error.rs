@@ -0,0 +1,107 @@
+//! Errors reported by the simulator to its clients.
+
+use std::fmt;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::from_simulator::CommandError;
+use crate::from_simulator::SimulationUpdate;
+use crate::handshake::IncompatibilityError;
+use crate::to_simulator::RequestId;
+
+/// Machine-readable category of a [`SimulationError`].
+///
+/// A failed request is reported as a [`CommandError`] in its
+/// [`CommandResult`](SimulationUpdate::CommandResult);
+/// the codes of such failures are only derived from it with [`CommandError::code`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// A command referenced a signal or signal element which doesn't exist.
+    UnknownSignal,
+    /// A [`RunUntil`](crate::to_simulator::RunUntil) deadline lies in the past.
+    DeadlineInPast,
+    /// A time is not a multiple of the simulator's time resolution.
+    UnalignedTime,
+    /// A signal pattern could not be compiled.
+    InvalidPattern,
+    /// A command referenced a breakpoint which doesn't exist.
+    UnknownBreakpoint,
+    /// A breakpoint's condition doesn't apply to its signal element.
+    InvalidBreakpoint,
+    /// A forced or deposited value doesn't fit the signal element's type.
+    InvalidValue,
+    /// The design could not be elaborated.
+    ElaborationFailed,
+    /// A command is not allowed in the current simulation state.
+    InvalidState,
+    /// A command is not implemented by the simulator.
+    Unsupported,
+    /// The client's protocol version or feature set is incompatible with the simulator.
+    IncompatibleProtocol,
+    /// A message from the client could not be decoded.
+    MalformedMessage,
+    /// An error inside the simulator or its adapter.
+    Internal,
+}
+
+/// An error reported by the simulator, sent as [`SimulationUpdate::Error`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SimulationError {
+    pub code: ErrorCode,
+    /// The request which caused the error, or `None` for errors which aren't related to a command.
+    pub request_id: Option<RequestId>,
+    /// A human-readable description of the error.
+    pub message: String,
+}
+
+impl SimulationError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            request_id: None,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the error for the failed request with the given ID, e.g. to log a failed
+    /// [`CommandResult`](SimulationUpdate::CommandResult) together with other errors.
+    pub fn for_command(request_id: RequestId, error: &CommandError) -> Self {
+        Self::new(error.code(), error.to_string()).for_request(request_id)
+    }
+
+    /// Attributes this error to the request with the given ID.
+    #[must_use]
+    pub const fn for_request(mut self, request_id: RequestId) -> Self {
+        self.request_id = Some(request_id);
+        self
+    }
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(request_id) = self.request_id {
+            write!(formatter, "request {request_id}: ")?;
+        }
+        write!(
+            formatter,
+            "{code:?}: {message}",
+            code = self.code,
+            message = self.message,
+        )
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+impl From<IncompatibilityError> for SimulationError {
+    fn from(error: IncompatibilityError) -> Self {
+        Self::new(ErrorCode::IncompatibleProtocol, error.to_string())
+    }
+}
+
+impl From<SimulationError> for SimulationUpdate {
+    fn from(error: SimulationError) -> Self {
+        SimulationUpdate::Error(error)
+    }
+}
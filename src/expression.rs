@@ -0,0 +1,1516 @@
+//! Boolean conditions over signal values, like `valid = '1' and ready = '1' and addr = x"40"`,
+//! used by [conditional breakpoints](crate::breakpoint::Breakpoint::Expression).
+//!
+//! The syntax follows VHDL:
+//! - signals are referenced by hierarchical path like `tb.dut.bus(3).valid`,
+//!   or by ID like `#12[3]` for element 3 of signal instance 12
+//! - literals are characters like `'1'`, bit strings like `"0101"`, `b"0101"`, `o"17"` and `x"40"`,
+//!   integers, reals like `1.5`, `true`, `false`, and enumeration literals like `IDLE`
+//! - relational operators are `=`, `/=`, `<`, `<=`, `>` and `>=`
+//! - logical operators are `not`, `and`, `or` and `xor`; different binary logical operators can't be mixed
+//!   without parentheses
+//! - `rising_edge(clk)` and `falling_edge(clk)` detect edges, and the `'event` attribute like `data'event`
+//!   detects any change in the current delta cycle
+//!
+//! Like with VHDL-2008's condition operator `??`, `Bit` and `Logic` values are true for `'1'` and `'H'`
+//! where a boolean is expected.
+//! Whole arrays of `Bit` or `Logic` are vectors which compare to bit strings of the same length.
+//!
+//! [`Expression::check`] resolves the signals and checks the types,
+//! yielding a [`Condition`] which adapters evaluate on every delta cycle.
+//!
+//! Expressions are nested at most [`MAX_DEPTH`] levels deep.
+
+use std::cell::Cell;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use compact_str::CompactString;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Logic;
+use crate::breakpoint::BreakCondition;
+use crate::design_hierarchy::SignalElementId;
+use crate::design_hierarchy::SignalInstanceId;
+use crate::design_hierarchy::SignalType;
+use crate::from_simulator::RawValue;
+use crate::hierarchy_index::HierarchyIndex;
+use crate::value::Value;
+use crate::value::ValueError;
+
+/// Maximum nesting depth of an [`Expression`], where signals and literals have depth 1.
+///
+/// Parsing, deserializing and [checking](Expression::check) reject deeper expressions,
+/// which bounds the recursion of all other operations on checked expressions.
+pub const MAX_DEPTH: usize = 64;
+
+/// A condition over signal values, as parsed from its textual form.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Expression {
+    Signal(SignalRef),
+    Literal(Literal),
+    Not(Box<Expression>),
+    Logical {
+        operator: LogicalOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    Relation {
+        operator: RelationalOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    Edge {
+        edge: Edge,
+        signal: SignalRef,
+    },
+}
+
+/// Reference to a scalar signal element or a whole signal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignalRef {
+    /// A hierarchical path as described in [`hierarchy_index`](crate::hierarchy_index).
+    ///
+    /// Paths which don't resolve to a signal are enumeration literals when compared to an enumeration.
+    Path(CompactString),
+    Element(SignalElementId),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Literal {
+    Boolean(bool),
+    Logic(Logic),
+    /// A bit string, leftmost element first.
+    BitString(Vec<Logic>),
+    Integer(i64),
+    Real(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogicalOperator {
+    And,
+    Or,
+    Xor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelationalOperator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Edge {
+    /// `rising_edge(signal)`
+    Rising,
+    /// `falling_edge(signal)`
+    Falling,
+    /// `signal'event`
+    Event,
+}
+
+impl Expression {
+    /// Resolves the signal references and checks the types of all operands.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a signal doesn't exist or isn't supported,
+    /// if the operand types don't fit the operators, or if the expression is nested too deeply.
+    pub fn check(&self, index: &HierarchyIndex<'_>) -> Result<Condition, ExpressionError> {
+        if self.depth() > MAX_DEPTH {
+            return Err(ExpressionError::TooDeep);
+        }
+        let mut checker = Checker {
+            index,
+            element_ids: BTreeSet::new(),
+        };
+        let (root, typ) = checker.check(self)?;
+        Ok(Condition {
+            root: to_boolean(root, typ)?,
+            element_ids: checker.element_ids.into_iter().collect(),
+        })
+    }
+
+    /// Returns the nesting depth of the expression, 1 for a signal or literal.
+    pub fn depth(&self) -> usize {
+        // iterative, so unchecked expressions of any depth don't overflow the stack
+        let mut depth = 0;
+        let mut pending = vec![(self, 1)];
+        while let Some((expression, level)) = pending.pop() {
+            depth = depth.max(level);
+            match expression {
+                Expression::Not(operand) => pending.push((operand, level + 1)),
+                Expression::Logical { left, right, .. }
+                | Expression::Relation { left, right, .. } => {
+                    pending.push((left, level + 1));
+                    pending.push((right, level + 1));
+                },
+                Expression::Signal(_) | Expression::Literal(_) | Expression::Edge { .. } => {},
+            }
+        }
+        depth
+    }
+
+    /// Returns `true` if the expression must be parenthesized as an operand of a relational operator or `not`.
+    const fn is_compound(&self) -> bool {
+        matches!(
+            self,
+            Expression::Logical { .. } | Expression::Relation { .. }
+        )
+    }
+}
+
+impl FromStr for Expression {
+    type Err = ExpressionError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            next: 0,
+            end: text.len(),
+            depth: 0,
+        };
+        let expression = parser.parse_expression()?;
+        match parser.tokens.get(parser.next) {
+            Some((position, _)) => Err(ExpressionError::syntax(*position, "expected an operator")),
+            None if expression.depth() > MAX_DEPTH => Err(ExpressionError::TooDeep),
+            None => Ok(expression),
+        }
+    }
+}
+
+/// Same as [`Expression`], for deserializing without the depth limit.
+#[derive(Deserialize)]
+#[serde(rename = "Expression")]
+enum UnlimitedExpression {
+    Signal(SignalRef),
+    Literal(Literal),
+    Not(Box<Expression>),
+    Logical {
+        operator: LogicalOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    Relation {
+        operator: RelationalOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    Edge {
+        edge: Edge,
+        signal: SignalRef,
+    },
+}
+
+impl From<UnlimitedExpression> for Expression {
+    fn from(expression: UnlimitedExpression) -> Self {
+        match expression {
+            UnlimitedExpression::Signal(signal) => Expression::Signal(signal),
+            UnlimitedExpression::Literal(literal) => Expression::Literal(literal),
+            UnlimitedExpression::Not(operand) => Expression::Not(operand),
+            UnlimitedExpression::Logical {
+                operator,
+                left,
+                right,
+            } => Expression::Logical {
+                operator,
+                left,
+                right,
+            },
+            UnlimitedExpression::Relation {
+                operator,
+                left,
+                right,
+            } => Expression::Relation {
+                operator,
+                left,
+                right,
+            },
+            UnlimitedExpression::Edge { edge, signal } => Expression::Edge { edge, signal },
+        }
+    }
+}
+
+thread_local! {
+    /// Nesting depth of the expression currently being deserialized.
+    static DESERIALIZATION_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Fails for expressions nested deeper than [`MAX_DEPTH`], before the recursion gets deeper.
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let depth = DESERIALIZATION_DEPTH.get();
+        if depth >= MAX_DEPTH {
+            return Err(serde::de::Error::custom(ExpressionError::TooDeep));
+        }
+        DESERIALIZATION_DEPTH.set(depth + 1);
+        let expression = UnlimitedExpression::deserialize(deserializer);
+        DESERIALIZATION_DEPTH.set(depth);
+        expression.map(Expression::from)
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Signal(signal) => write!(formatter, "{signal}"),
+            Expression::Literal(literal) => write!(formatter, "{literal}"),
+            Expression::Not(operand) if operand.is_compound() => {
+                write!(formatter, "not ({operand})")
+            },
+            Expression::Not(operand) => write!(formatter, "not {operand}"),
+            Expression::Logical {
+                operator,
+                left,
+                right,
+            } => {
+                // relations bind tighter than logical operators and need no parentheses
+                match &**left {
+                    Expression::Logical {
+                        operator: left_operator,
+                        ..
+                    } if left_operator != operator => write!(formatter, "({left})")?,
+                    left => write!(formatter, "{left}")?,
+                }
+                write!(formatter, " {operator} ")?;
+                match &**right {
+                    right @ Expression::Logical { .. } => write!(formatter, "({right})"),
+                    right => write!(formatter, "{right}"),
+                }
+            },
+            Expression::Relation {
+                operator,
+                left,
+                right,
+            } => {
+                let parenthesized = |operand: &Expression| {
+                    if operand.is_compound() {
+                        format!("({operand})")
+                    } else {
+                        operand.to_string()
+                    }
+                };
+                write!(
+                    formatter,
+                    "{left} {operator} {right}",
+                    left = parenthesized(left),
+                    right = parenthesized(right),
+                )
+            },
+            Expression::Edge {
+                edge: Edge::Rising,
+                signal,
+            } => write!(formatter, "rising_edge({signal})"),
+            Expression::Edge {
+                edge: Edge::Falling,
+                signal,
+            } => write!(formatter, "falling_edge({signal})"),
+            Expression::Edge {
+                edge: Edge::Event,
+                signal,
+            } => write!(formatter, "{signal}'event"),
+        }
+    }
+}
+
+impl fmt::Display for SignalRef {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignalRef::Path(path) => formatter.write_str(path),
+            SignalRef::Element(element_id) => write!(
+                formatter,
+                "#{signal}[{element}]",
+                signal = element_id.signal_id,
+                element = element_id.element_index,
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Boolean(value) => write!(formatter, "{value}"),
+            Literal::Logic(value) => write!(formatter, "'{value}'"),
+            Literal::BitString(values) => {
+                formatter.write_str("\"")?;
+                for value in values {
+                    write!(formatter, "{value}")?;
+                }
+                formatter.write_str("\"")
+            },
+            Literal::Integer(value) => write!(formatter, "{value}"),
+            // overflows to infinity when parsed
+            Literal::Real(value) if value.is_infinite() => {
+                let sign = if value.is_sign_negative() { "-" } else { "" };
+                write!(formatter, "{sign}1.0e999")
+            },
+            Literal::Real(value) => {
+                // real literals need a decimal point to be parsed as reals again
+                let text = format!("{value:?}");
+                match text.split_once('e') {
+                    Some((mantissa, exponent)) if !mantissa.contains('.') => {
+                        write!(formatter, "{mantissa}.0e{exponent}")
+                    },
+                    _ => formatter.write_str(&text),
+                }
+            },
+        }
+    }
+}
+
+impl fmt::Display for LogicalOperator {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            LogicalOperator::And => "and",
+            LogicalOperator::Or => "or",
+            LogicalOperator::Xor => "xor",
+        })
+    }
+}
+
+impl fmt::Display for RelationalOperator {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            RelationalOperator::Equal => "=",
+            RelationalOperator::NotEqual => "/=",
+            RelationalOperator::Less => "<",
+            RelationalOperator::LessEqual => "<=",
+            RelationalOperator::Greater => ">",
+            RelationalOperator::GreaterEqual => ">=",
+        })
+    }
+}
+
+/// Type of an operand in an [`Expression`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpressionType {
+    Boolean,
+    /// A `Bit` or `Logic` value.
+    Logic,
+    Integer,
+    Real,
+    Enumeration(Vec<CompactString>),
+    /// An array of `Bit` or `Logic` values with the given length.
+    Vector(u32),
+}
+
+impl fmt::Display for ExpressionType {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpressionType::Boolean => formatter.write_str("boolean"),
+            ExpressionType::Logic => formatter.write_str("bit or logic"),
+            ExpressionType::Integer => formatter.write_str("integer"),
+            ExpressionType::Real => formatter.write_str("real"),
+            ExpressionType::Enumeration(names) => {
+                write!(formatter, "enumeration ({names})", names = names.join(", "))
+            },
+            ExpressionType::Vector(length) => write!(formatter, "vector of length {length}"),
+        }
+    }
+}
+
+/// Error when parsing, checking or evaluating an [`Expression`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionError {
+    /// The text is no valid expression; `position` is the byte offset of the offending token.
+    Syntax {
+        position: usize,
+        message: String,
+    },
+    UnknownSignal(SignalRef),
+    /// The signal is neither scalar nor an array of `Bit` or `Logic`.
+    UnsupportedSignal(SignalRef),
+    /// The operands of a relational operator have different types.
+    TypeMismatch {
+        left: ExpressionType,
+        right: ExpressionType,
+    },
+    /// An operand of a logical operator, or the whole condition, is neither boolean nor `Bit` or `Logic`.
+    NotBoolean(ExpressionType),
+    /// The argument of `rising_edge` or `falling_edge` is not a scalar `Bit` or `Logic` signal.
+    NotLogic(SignalRef),
+    /// The [`ValueSource`] has no value for the element.
+    MissingValue(SignalElementId),
+    /// The [`ValueSource`] returned a value which is invalid for the element's type.
+    InvalidValue(ValueError),
+    /// The expression is nested deeper than [`MAX_DEPTH`].
+    TooDeep,
+    /// A real literal is NaN, which has no textual form and never compares equal.
+    NotANumber,
+}
+
+impl ExpressionError {
+    fn syntax(position: usize, message: impl Into<String>) -> Self {
+        ExpressionError::Syntax {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpressionError::Syntax { position, message } => {
+                write!(formatter, "{message} at position {position}")
+            },
+            ExpressionError::UnknownSignal(signal) => write!(formatter, "unknown signal {signal}"),
+            ExpressionError::UnsupportedSignal(signal) => {
+                write!(
+                    formatter,
+                    "signal {signal} is neither scalar nor a bit or logic vector"
+                )
+            },
+            ExpressionError::TypeMismatch { left, right } => {
+                write!(formatter, "cannot compare {left} with {right}")
+            },
+            ExpressionError::NotBoolean(typ) => {
+                write!(formatter, "expected a boolean, found {typ}")
+            },
+            ExpressionError::NotLogic(signal) => {
+                write!(formatter, "edge of {signal} requires a bit or logic signal")
+            },
+            ExpressionError::MissingValue(element_id) => {
+                write!(formatter, "missing value of {element_id:?}")
+            },
+            ExpressionError::InvalidValue(error) => write!(formatter, "invalid value: {error}"),
+            ExpressionError::TooDeep => {
+                write!(
+                    formatter,
+                    "expression is nested deeper than {MAX_DEPTH} levels"
+                )
+            },
+            ExpressionError::NotANumber => formatter.write_str("real literal is NaN"),
+        }
+    }
+}
+
+impl Error for ExpressionError {}
+
+impl From<ValueError> for ExpressionError {
+    fn from(error: ValueError) -> Self {
+        ExpressionError::InvalidValue(error)
+    }
+}
+
+/// Values of signal elements at the end of the current delta cycle and before it.
+///
+/// Implemented by simulator adapters to [evaluate](Condition::evaluate) conditions.
+pub trait ValueSource {
+    /// Returns the value of the element at the end of the current delta cycle.
+    fn value(&self, element_id: SignalElementId) -> Option<RawValue>;
+
+    /// Returns the value of the element before the current delta cycle,
+    /// which equals [`Self::value`] if the element didn't change.
+    fn previous_value(&self, element_id: SignalElementId) -> Option<RawValue>;
+}
+
+/// A [checked](Expression::check) expression, ready for evaluation.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    root: Node,
+    element_ids: Vec<SignalElementId>,
+}
+
+impl Condition {
+    /// Returns the scalar elements the condition depends on, in ascending order.
+    pub fn element_ids(&self) -> &[SignalElementId] {
+        &self.element_ids
+    }
+
+    /// Evaluates the condition with the values at the end of the current delta cycle.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source lacks a value, or returns a value which is invalid for its element.
+    pub fn evaluate(&self, source: &impl ValueSource) -> Result<bool, ExpressionError> {
+        self.root.evaluate_boolean(source)
+    }
+
+    /// Returns `true` if one of the [elements](Self::element_ids) changed in the current delta cycle
+    /// and the condition is true afterwards, like VHDL's `wait until`.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::evaluate`].
+    pub fn fires(&self, source: &impl ValueSource) -> Result<bool, ExpressionError> {
+        let changed = self
+            .element_ids
+            .iter()
+            .any(|&element_id| source.previous_value(element_id) != source.value(element_id));
+        Ok(changed && self.evaluate(source)?)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Constant(Operand),
+    Scalar {
+        element_id: SignalElementId,
+        typ: SignalType,
+    },
+    Vector {
+        element_ids: Vec<SignalElementId>,
+        typ: SignalType,
+    },
+    /// Converts a `Bit` or `Logic` value to a boolean, like VHDL's `??`.
+    Condition(Box<Node>),
+    Not(Box<Node>),
+    Logical {
+        operator: LogicalOperator,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    Relation {
+        operator: RelationalOperator,
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    Edge {
+        condition: BreakCondition,
+        element_ids: Vec<SignalElementId>,
+        typ: SignalType,
+    },
+}
+
+/// Value of a [`Node`]; variants are only compared to the same variant after type checking.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+enum Operand {
+    Boolean(bool),
+    Logic(Logic),
+    Integer(i64),
+    Real(f64),
+    Enumeration(u32),
+    Vector(Vec<Logic>),
+}
+
+impl Node {
+    fn evaluate(&self, source: &impl ValueSource) -> Result<Operand, ExpressionError> {
+        Ok(match self {
+            Node::Constant(operand) => operand.clone(),
+            Node::Scalar { element_id, typ } => scalar_operand(typ, value(source, *element_id)?)?,
+            Node::Vector { element_ids, typ } => {
+                let mut values = Vec::with_capacity(element_ids.len());
+                for &element_id in element_ids {
+                    values.push(match scalar_operand(typ, value(source, element_id)?)? {
+                        Operand::Logic(logic) => logic,
+                        _ => unreachable!("vector elements are bit or logic"),
+                    });
+                }
+                Operand::Vector(values)
+            },
+            Node::Condition(operand) => Operand::Boolean(matches!(
+                operand.evaluate(source)?,
+                Operand::Logic(Logic::One | Logic::H)
+            )),
+            Node::Not(operand) => Operand::Boolean(!operand.evaluate_boolean(source)?),
+            Node::Logical {
+                operator,
+                left,
+                right,
+            } => {
+                let left = left.evaluate_boolean(source)?;
+                Operand::Boolean(match operator {
+                    LogicalOperator::And => left && right.evaluate_boolean(source)?,
+                    LogicalOperator::Or => left || right.evaluate_boolean(source)?,
+                    LogicalOperator::Xor => left != right.evaluate_boolean(source)?,
+                })
+            },
+            Node::Relation {
+                operator,
+                left,
+                right,
+            } => {
+                let left = left.evaluate(source)?;
+                let right = right.evaluate(source)?;
+                Operand::Boolean(match operator {
+                    RelationalOperator::Equal => left == right,
+                    RelationalOperator::NotEqual => left != right,
+                    RelationalOperator::Less => left < right,
+                    RelationalOperator::LessEqual => left <= right,
+                    RelationalOperator::Greater => left > right,
+                    RelationalOperator::GreaterEqual => left >= right,
+                })
+            },
+            Node::Edge {
+                condition,
+                element_ids,
+                typ,
+            } => {
+                let mut fired = false;
+                for &element_id in element_ids {
+                    let previous = source
+                        .previous_value(element_id)
+                        .ok_or(ExpressionError::MissingValue(element_id))?;
+                    fired |= condition.fires(typ, previous, value(source, element_id)?);
+                }
+                Operand::Boolean(fired)
+            },
+        })
+    }
+
+    fn evaluate_boolean(&self, source: &impl ValueSource) -> Result<bool, ExpressionError> {
+        Ok(matches!(self.evaluate(source)?, Operand::Boolean(true)))
+    }
+}
+
+fn value(
+    source: &impl ValueSource,
+    element_id: SignalElementId,
+) -> Result<RawValue, ExpressionError> {
+    source
+        .value(element_id)
+        .ok_or(ExpressionError::MissingValue(element_id))
+}
+
+fn scalar_operand(typ: &SignalType, raw: RawValue) -> Result<Operand, ExpressionError> {
+    Ok(match typ.decode(raw)? {
+        Value::Bit(false) => Operand::Logic(Logic::Zero),
+        Value::Bit(true) => Operand::Logic(Logic::One),
+        Value::Logic(logic) => Operand::Logic(logic),
+        Value::Integer(integer) => Operand::Integer(integer),
+        Value::Real(real) => Operand::Real(real),
+        Value::Enumeration(index) => Operand::Enumeration(index),
+    })
+}
+
+fn to_boolean(node: Node, typ: ExpressionType) -> Result<Node, ExpressionError> {
+    match typ {
+        ExpressionType::Boolean => Ok(node),
+        ExpressionType::Logic => Ok(Node::Condition(Box::new(node))),
+        typ => Err(ExpressionError::NotBoolean(typ)),
+    }
+}
+
+struct Checker<'index, 'a> {
+    index: &'index HierarchyIndex<'a>,
+    element_ids: BTreeSet<SignalElementId>,
+}
+
+impl Checker<'_, '_> {
+    fn check(
+        &mut self,
+        expression: &Expression,
+    ) -> Result<(Node, ExpressionType), ExpressionError> {
+        Ok(match expression {
+            Expression::Signal(signal) => self.resolve(signal)?,
+            Expression::Literal(literal) => match literal {
+                Literal::Boolean(value) => (
+                    Node::Constant(Operand::Boolean(*value)),
+                    ExpressionType::Boolean,
+                ),
+                Literal::Logic(value) => (
+                    Node::Constant(Operand::Logic(*value)),
+                    ExpressionType::Logic,
+                ),
+                Literal::BitString(values) => (
+                    Node::Constant(Operand::Vector(values.clone())),
+                    ExpressionType::Vector(values.len().try_into().unwrap_or(u32::MAX)),
+                ),
+                Literal::Integer(value) => (
+                    Node::Constant(Operand::Integer(*value)),
+                    ExpressionType::Integer,
+                ),
+                Literal::Real(value) if value.is_nan() => return Err(ExpressionError::NotANumber),
+                Literal::Real(value) => {
+                    (Node::Constant(Operand::Real(*value)), ExpressionType::Real)
+                },
+            },
+            Expression::Not(operand) => {
+                let (operand, typ) = self.check(operand)?;
+                (
+                    Node::Not(Box::new(to_boolean(operand, typ)?)),
+                    ExpressionType::Boolean,
+                )
+            },
+            Expression::Logical {
+                operator,
+                left,
+                right,
+            } => {
+                let (left, left_type) = self.check(left)?;
+                let (right, right_type) = self.check(right)?;
+                let node = Node::Logical {
+                    operator: *operator,
+                    left: Box::new(to_boolean(left, left_type)?),
+                    right: Box::new(to_boolean(right, right_type)?),
+                };
+                (node, ExpressionType::Boolean)
+            },
+            Expression::Relation {
+                operator,
+                left,
+                right,
+            } => {
+                let ((left, left_type), (right, right_type)) =
+                    match (self.check(left), self.check(right)) {
+                        (Ok(left), Ok(right)) => (left, right),
+                        (Err(error), Ok(right)) => {
+                            (enumeration_literal(left, &right.1).ok_or(error)?, right)
+                        },
+                        (Ok(left), Err(error)) => {
+                            let right = enumeration_literal(right, &left.1).ok_or(error)?;
+                            (left, right)
+                        },
+                        (Err(error), Err(_)) => return Err(error),
+                    };
+                if left_type != right_type {
+                    return Err(ExpressionError::TypeMismatch {
+                        left: left_type,
+                        right: right_type,
+                    });
+                }
+                let node = Node::Relation {
+                    operator: *operator,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                };
+                (node, ExpressionType::Boolean)
+            },
+            Expression::Edge { edge, signal } => {
+                let (element_ids, typ) = match self.resolve(signal)? {
+                    (Node::Scalar { element_id, typ }, ExpressionType::Logic) => {
+                        (vec![element_id], typ)
+                    },
+                    _ if *edge != Edge::Event => {
+                        return Err(ExpressionError::NotLogic(signal.clone()));
+                    },
+                    (Node::Scalar { element_id, typ }, _) => (vec![element_id], typ),
+                    (Node::Vector { element_ids, typ }, _) => (element_ids, typ),
+                    _ => unreachable!("signals resolve to scalars or vectors"),
+                };
+                let condition = match edge {
+                    Edge::Rising => BreakCondition::Rising,
+                    Edge::Falling => BreakCondition::Falling,
+                    Edge::Event => BreakCondition::Change,
+                };
+                let node = Node::Edge {
+                    condition,
+                    element_ids,
+                    typ,
+                };
+                (node, ExpressionType::Boolean)
+            },
+        })
+    }
+
+    /// Resolves a signal to a [`Node::Scalar`] or [`Node::Vector`].
+    fn resolve(&mut self, signal: &SignalRef) -> Result<(Node, ExpressionType), ExpressionError> {
+        let unknown = || ExpressionError::UnknownSignal(signal.clone());
+        let unsupported = || ExpressionError::UnsupportedSignal(signal.clone());
+        let element_id = match signal {
+            SignalRef::Element(element_id) => *element_id,
+            SignalRef::Path(path) => match self.index.element_id(path) {
+                Some(element_id) => element_id,
+                None => {
+                    let signal_id = self.index.signal_id(path).ok_or_else(unknown)?;
+                    return self.resolve_vector(signal_id).ok_or_else(unsupported);
+                },
+            },
+        };
+        let typ = self
+            .index
+            .signal(element_id.signal_id)
+            .and_then(|signal| signal.typ.element_type(element_id.element_index))
+            .ok_or_else(unknown)?;
+        let expression_type = match typ {
+            SignalType::Bit | SignalType::Logic => ExpressionType::Logic,
+            SignalType::Integer { .. } => ExpressionType::Integer,
+            SignalType::Real { .. } => ExpressionType::Real,
+            SignalType::Enumeration { names } => ExpressionType::Enumeration(names.clone()),
+            _ => return Err(unsupported()),
+        };
+        self.element_ids.insert(element_id);
+        let node = Node::Scalar {
+            element_id,
+            typ: typ.clone(),
+        };
+        Ok((node, expression_type))
+    }
+
+    /// Resolves an array of `Bit` or `Logic` to a [`Node::Vector`].
+    fn resolve_vector(&mut self, signal_id: SignalInstanceId) -> Option<(Node, ExpressionType)> {
+        let SignalType::Array {
+            element_count,
+            element_type,
+            ..
+        } = &self.index.signal(signal_id)?.typ
+        else {
+            return None;
+        };
+        if !matches!(**element_type, SignalType::Bit | SignalType::Logic) {
+            return None;
+        }
+        let element_ids: Vec<_> = (0..*element_count)
+            .map(|element_index| SignalElementId::new(signal_id, element_index))
+            .collect();
+        self.element_ids.extend(&element_ids);
+        let node = Node::Vector {
+            element_ids,
+            typ: (**element_type).clone(),
+        };
+        Some((node, ExpressionType::Vector(*element_count)))
+    }
+}
+
+/// Resolves an unknown signal path to a literal of the enumeration type it's compared to.
+fn enumeration_literal(
+    expression: &Expression,
+    other: &ExpressionType,
+) -> Option<(Node, ExpressionType)> {
+    let (Expression::Signal(SignalRef::Path(name)), ExpressionType::Enumeration(names)) =
+        (expression, other)
+    else {
+        return None;
+    };
+    let index = names
+        .iter()
+        .position(|literal| literal.eq_ignore_ascii_case(name))?;
+    let operand = Operand::Enumeration(index.try_into().ok()?);
+    Some((Node::Constant(operand), other.clone()))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(CompactString),
+    /// An integer without sign, so that the magnitude of `i64::MIN` fits.
+    Integer(u64),
+    Real(f64),
+    Character(Logic),
+    BitString(Vec<Logic>),
+    /// The `'` of an attribute like `'event`.
+    Apostrophe,
+    LeftParenthesis,
+    RightParenthesis,
+    LeftBracket,
+    RightBracket,
+    Dot,
+    Hash,
+    Minus,
+    Relational(RelationalOperator),
+}
+
+const KEYWORDS: &[&str] = &["and", "or", "xor", "not", "true", "false"];
+
+fn is_keyword(identifier: &str) -> bool {
+    KEYWORDS
+        .iter()
+        .any(|keyword| keyword.eq_ignore_ascii_case(identifier))
+}
+
+/// Splits the text into tokens with their byte offsets.
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, ExpressionError> {
+    let mut tokens: Vec<(usize, Token)> = vec![];
+    let mut chars = text.char_indices().peekable();
+    while let Some((position, ch)) = chars.next() {
+        let token = match ch {
+            _ if ch.is_whitespace() => continue,
+            _ if ch.is_ascii_alphabetic() => {
+                let mut end = position + ch.len_utf8();
+                while let Some((_, next)) =
+                    chars.next_if(|(_, next)| next.is_ascii_alphanumeric() || *next == '_')
+                {
+                    end += next.len_utf8();
+                }
+                let identifier = &text[position..end];
+                let bits_per_digit = match identifier {
+                    "b" | "B" => Some(1),
+                    "o" | "O" => Some(3),
+                    "x" | "X" => Some(4),
+                    _ => None,
+                };
+                match bits_per_digit {
+                    Some(bits_per_digit) if chars.next_if(|(_, next)| *next == '"').is_some() => {
+                        Token::BitString(bit_string(&mut chars, position, bits_per_digit)?)
+                    },
+                    _ => Token::Identifier(identifier.into()),
+                }
+            },
+            _ if ch.is_ascii_digit() => {
+                let (length, is_real) = number_length(&text[position..]);
+                while chars
+                    .next_if(|(index, _)| *index < position + length)
+                    .is_some()
+                {}
+                let number = text[position..position + length].replace('_', "");
+                let token = if is_real {
+                    number.parse().ok().map(Token::Real)
+                } else {
+                    number.parse().ok().map(Token::Integer)
+                };
+                token.ok_or_else(|| ExpressionError::syntax(position, "invalid number"))?
+            },
+            '"' => Token::BitString(bit_string(&mut chars, position, 1)?),
+            '\'' => match tokens.last() {
+                Some((_, Token::Identifier(identifier))) if !is_keyword(identifier) => {
+                    Token::Apostrophe
+                },
+                Some((_, Token::RightParenthesis | Token::RightBracket)) => Token::Apostrophe,
+                // an element ID like `#12`
+                Some((_, Token::Integer(_)))
+                    if matches!(tokens.iter().rev().nth(1), Some((_, Token::Hash))) =>
+                {
+                    Token::Apostrophe
+                },
+                _ => {
+                    let value = chars
+                        .next()
+                        .and_then(|(_, value)| logic(value))
+                        .filter(|_| chars.next_if(|(_, next)| *next == '\'').is_some())
+                        .ok_or_else(|| ExpressionError::syntax(position, "invalid character"))?;
+                    Token::Character(value)
+                },
+            },
+            '(' => Token::LeftParenthesis,
+            ')' => Token::RightParenthesis,
+            '[' => Token::LeftBracket,
+            ']' => Token::RightBracket,
+            '.' => Token::Dot,
+            '#' => Token::Hash,
+            '-' => Token::Minus,
+            '=' => Token::Relational(RelationalOperator::Equal),
+            '/' if chars.next_if(|(_, next)| *next == '=').is_some() => {
+                Token::Relational(RelationalOperator::NotEqual)
+            },
+            '<' if chars.next_if(|(_, next)| *next == '=').is_some() => {
+                Token::Relational(RelationalOperator::LessEqual)
+            },
+            '<' => Token::Relational(RelationalOperator::Less),
+            '>' if chars.next_if(|(_, next)| *next == '=').is_some() => {
+                Token::Relational(RelationalOperator::GreaterEqual)
+            },
+            '>' => Token::Relational(RelationalOperator::Greater),
+            _ => return Err(ExpressionError::syntax(position, "unexpected character")),
+        };
+        tokens.push((position, token));
+    }
+    Ok(tokens)
+}
+
+/// Returns the length of the number at the start of the text, and whether it is a real number,
+/// i.e. has a fraction or an exponent.
+fn number_length(text: &str) -> (usize, bool) {
+    let bytes = text.as_bytes();
+    let digits_end = |start: usize| {
+        start
+            + bytes[start..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_digit() || **byte == b'_')
+                .count()
+    };
+    let mut end = digits_end(0);
+    let mut is_real = false;
+    if bytes.get(end) == Some(&b'.') && bytes.get(end + 1).is_some_and(u8::is_ascii_digit) {
+        end = digits_end(end + 1);
+        is_real = true;
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let mut exponent = end + 1;
+        if matches!(bytes.get(exponent), Some(b'+' | b'-')) {
+            exponent += 1;
+        }
+        if bytes.get(exponent).is_some_and(u8::is_ascii_digit) {
+            end = digits_end(exponent);
+            is_real = true;
+        }
+    }
+    (end, is_real)
+}
+
+/// Parses the logic value of a character, like `'1'` or `'Z'`.
+fn logic(ch: char) -> Option<Logic> {
+    ch.to_ascii_uppercase().to_string().parse().ok()
+}
+
+/// Reads the rest of a bit string after its opening quote.
+///
+/// Digits expand to `bits_per_digit` bits; other logic values like `Z` are repeated as often, like in VHDL-2008.
+fn bit_string(
+    chars: &mut impl Iterator<Item = (usize, char)>,
+    position: usize,
+    bits_per_digit: u32,
+) -> Result<Vec<Logic>, ExpressionError> {
+    let mut values = vec![];
+    loop {
+        let Some((_, ch)) = chars.next() else {
+            return Err(ExpressionError::syntax(position, "unterminated bit string"));
+        };
+        if ch == '"' {
+            return Ok(values);
+        }
+        if ch == '_' {
+            continue;
+        }
+        match ch.to_digit(1 << bits_per_digit) {
+            Some(digit) => values.extend((0..bits_per_digit).rev().map(|bit| {
+                if digit >> bit & 1 == 1 {
+                    Logic::One
+                } else {
+                    Logic::Zero
+                }
+            })),
+            None => {
+                let value = logic(ch)
+                    .ok_or_else(|| ExpressionError::syntax(position, "invalid bit string"))?;
+                values.extend((0..bits_per_digit).map(|_| value));
+            },
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    /// Byte offset of the end of the text, for errors at the end.
+    end: usize,
+    /// Number of enclosing parentheses and `not` operators.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.next)
+            .map_or(self.end, |(position, _)| *position)
+    }
+
+    fn error(&self, message: &str) -> ExpressionError {
+        ExpressionError::syntax(self.position(), message)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next)?.1.clone();
+        self.next += 1;
+        Some(token)
+    }
+
+    fn expect(&mut self, expected: &Token, message: &str) -> Result<(), ExpressionError> {
+        if self.peek() == Some(expected) {
+            self.next += 1;
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    /// Parses a nested operand, failing if the nesting gets deeper than [`MAX_DEPTH`].
+    fn nested(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<Expression, ExpressionError>,
+    ) -> Result<Expression, ExpressionError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(ExpressionError::TooDeep);
+        }
+        self.depth += 1;
+        let expression = parse(self);
+        self.depth -= 1;
+        expression
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Identifier(identifier)) if identifier.eq_ignore_ascii_case(keyword))
+    }
+
+    /// Returns the edge if the next tokens are `rising_edge(` or `falling_edge(`.
+    fn peek_edge_function(&self) -> Option<Edge> {
+        if self.tokens.get(self.next + 1).map(|(_, token)| token) != Some(&Token::LeftParenthesis) {
+            return None;
+        }
+        if self.peek_keyword("rising_edge") {
+            Some(Edge::Rising)
+        } else if self.peek_keyword("falling_edge") {
+            Some(Edge::Falling)
+        } else {
+            None
+        }
+    }
+
+    fn parse_expression(&mut self) -> Result<Expression, ExpressionError> {
+        let mut expression = self.parse_relation()?;
+        let mut previous_operator = None;
+        loop {
+            let operator = if self.peek_keyword("and") {
+                LogicalOperator::And
+            } else if self.peek_keyword("or") {
+                LogicalOperator::Or
+            } else if self.peek_keyword("xor") {
+                LogicalOperator::Xor
+            } else {
+                return Ok(expression);
+            };
+            if previous_operator.is_some_and(|previous| previous != operator) {
+                return Err(self.error("mixed logical operators require parentheses"));
+            }
+            previous_operator = Some(operator);
+            self.next += 1;
+            expression = Expression::Logical {
+                operator,
+                left: Box::new(expression),
+                right: Box::new(self.parse_relation()?),
+            };
+        }
+    }
+
+    fn parse_relation(&mut self) -> Result<Expression, ExpressionError> {
+        let left = self.parse_factor()?;
+        let Some(&Token::Relational(operator)) = self.peek() else {
+            return Ok(left);
+        };
+        self.next += 1;
+        Ok(Expression::Relation {
+            operator,
+            left: Box::new(left),
+            right: Box::new(self.parse_factor()?),
+        })
+    }
+
+    fn parse_factor(&mut self) -> Result<Expression, ExpressionError> {
+        if self.peek_keyword("not") {
+            self.next += 1;
+            let operand = self.nested(Self::parse_factor)?;
+            return Ok(Expression::Not(Box::new(operand)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, ExpressionError> {
+        let position = self.position();
+        let expected_operand = || ExpressionError::syntax(position, "expected an operand");
+        if let Some(edge) = self.peek_edge_function() {
+            self.next += 2;
+            let signal = self.parse_signal()?;
+            self.expect(&Token::RightParenthesis, "expected ')'")?;
+            return Ok(Expression::Edge { edge, signal });
+        }
+        let literal = match self.peek().ok_or_else(expected_operand)? {
+            Token::LeftParenthesis => {
+                self.next += 1;
+                let expression = self.nested(Self::parse_expression)?;
+                self.expect(&Token::RightParenthesis, "expected ')'")?;
+                return Ok(expression);
+            },
+            Token::Character(value) => Literal::Logic(*value),
+            Token::BitString(values) => Literal::BitString(values.clone()),
+            Token::Integer(value) => Literal::Integer(
+                i64::try_from(*value).map_err(|_| self.error("integer out of range"))?,
+            ),
+            Token::Real(value) => Literal::Real(*value),
+            Token::Minus => {
+                self.next += 1;
+                match self.peek() {
+                    Some(Token::Integer(value)) => Literal::Integer(
+                        0_i64
+                            .checked_sub_unsigned(*value)
+                            .ok_or_else(|| self.error("integer out of range"))?,
+                    ),
+                    Some(Token::Real(value)) => Literal::Real(-value),
+                    _ => return Err(self.error("expected a number")),
+                }
+            },
+            Token::Identifier(identifier) if identifier.eq_ignore_ascii_case("true") => {
+                Literal::Boolean(true)
+            },
+            Token::Identifier(identifier) if identifier.eq_ignore_ascii_case("false") => {
+                Literal::Boolean(false)
+            },
+            Token::Identifier(identifier) if is_keyword(identifier) => {
+                return Err(expected_operand());
+            },
+            Token::Identifier(_) | Token::Hash => {
+                let signal = self.parse_signal()?;
+                if self.peek() != Some(&Token::Apostrophe) {
+                    return Ok(Expression::Signal(signal));
+                }
+                self.next += 1;
+                if !self.peek_keyword("event") {
+                    return Err(self.error("expected 'event"));
+                }
+                self.next += 1;
+                return Ok(Expression::Edge {
+                    edge: Edge::Event,
+                    signal,
+                });
+            },
+            _ => return Err(expected_operand()),
+        };
+        self.next += 1;
+        Ok(Expression::Literal(literal))
+    }
+
+    /// Parses a path like `tb.dut.bus(3).valid` or an element ID like `#12[3]`.
+    fn parse_signal(&mut self) -> Result<SignalRef, ExpressionError> {
+        if self.peek() == Some(&Token::Hash) {
+            self.next += 1;
+            let signal_id = match self.advance() {
+                Some(Token::Integer(id)) => u32::try_from(id)
+                    .ok()
+                    .and_then(|id| id.try_into().ok())
+                    .map(SignalInstanceId),
+                _ => None,
+            };
+            let signal_id = signal_id.ok_or_else(|| self.error("expected a signal ID"))?;
+            let mut element_index = 0;
+            if self.peek() == Some(&Token::LeftBracket) {
+                self.next += 1;
+                element_index = match self.advance() {
+                    Some(Token::Integer(index)) => u32::try_from(index).ok(),
+                    _ => None,
+                }
+                .ok_or_else(|| self.error("expected an element index"))?;
+                self.expect(&Token::RightBracket, "expected ']'")?;
+            }
+            return Ok(SignalRef::Element(SignalElementId::new(
+                signal_id,
+                element_index,
+            )));
+        }
+
+        let mut path = match self.advance() {
+            Some(Token::Identifier(identifier)) if !is_keyword(&identifier) => identifier,
+            _ => return Err(self.error("expected a signal")),
+        };
+        loop {
+            match self.peek() {
+                Some(Token::Dot) => {
+                    self.next += 1;
+                    match self.advance() {
+                        Some(Token::Identifier(identifier)) => {
+                            path.push('.');
+                            path.push_str(&identifier);
+                        },
+                        _ => return Err(self.error("expected a name")),
+                    }
+                },
+                Some(Token::LeftParenthesis) => {
+                    self.next += 1;
+                    let negative = self.peek() == Some(&Token::Minus);
+                    if negative {
+                        self.next += 1;
+                    }
+                    let index = match self.advance() {
+                        Some(Token::Integer(index)) if negative => {
+                            0_i64.checked_sub_unsigned(index)
+                        },
+                        Some(Token::Integer(index)) => i64::try_from(index).ok(),
+                        _ => None,
+                    }
+                    .ok_or_else(|| self.error("expected an index"))?;
+                    path.push_str(&format!("({index})"));
+                    self.expect(&Token::RightParenthesis, "expected ')'")?;
+                },
+                _ => return Ok(SignalRef::Path(path)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::design_hierarchy::DesignHierarchy;
+    use crate::test_utils::logic_vector;
+    use crate::test_utils::module;
+    use crate::test_utils::signal;
+
+    fn hierarchy() -> DesignHierarchy {
+        let state = SignalType::Enumeration {
+            names: vec!["IDLE".into(), "BUSY".into()],
+        };
+        let signals = vec![
+            signal("clk", 1, SignalType::Bit),
+            signal("valid", 2, SignalType::Logic),
+            signal("ready", 3, SignalType::Logic),
+            signal("addr", 4, logic_vector(7, 0)),
+            signal("state", 5, state),
+        ];
+        crate::test_utils::hierarchy(vec![module("tb", vec![], signals)])
+    }
+
+    /// Maps elements to their previous and current values.
+    struct Values(HashMap<SignalElementId, (RawValue, RawValue)>);
+
+    impl Values {
+        fn set(&mut self, index: &HierarchyIndex<'_>, path: &str, previous: u64, current: u64) {
+            let element_id = index.element_id(path).unwrap();
+            self.0
+                .insert(element_id, (RawValue(previous), RawValue(current)));
+        }
+    }
+
+    impl ValueSource for Values {
+        fn value(&self, element_id: SignalElementId) -> Option<RawValue> {
+            Some(self.0.get(&element_id)?.1)
+        }
+
+        fn previous_value(&self, element_id: SignalElementId) -> Option<RawValue> {
+            Some(self.0.get(&element_id)?.0)
+        }
+    }
+
+    #[test]
+    fn parses_and_displays_expressions() {
+        let cases = [
+            (
+                "valid = '1' AND ready = '1' and tb.addr = X\"4_0\"",
+                "valid = '1' and ready = '1' and tb.addr = \"01000000\"",
+            ),
+            (
+                "not (a or b) xor rising_edge(tb.clk)",
+                "not (a or b) xor rising_edge(tb.clk)",
+            ),
+            (
+                "bus(-1).data'event or #12[3] >= -1.5e3",
+                "bus(-1).data'event or #12[3] >= -1500.0",
+            ),
+            ("#12'event and #12", "#12[0]'event and #12[0]"),
+            (
+                "state /= IDLE and (o\"7\" < b\"1Z0\")",
+                "state /= IDLE and \"111\" < \"1Z0\"",
+            ),
+        ];
+        for (text, display) in cases {
+            let expression: Expression = text.parse().unwrap();
+            assert_eq!(expression.to_string(), display);
+            assert_eq!(display.parse::<Expression>().unwrap(), expression);
+        }
+
+        for (text, position) in [
+            ("a and b or c", 8),
+            ("a = b = c", 6),
+            ("a and", 5),
+            ("x\"4", 0),
+            ("'2'", 0),
+        ] {
+            match text.parse::<Expression>() {
+                Err(ExpressionError::Syntax {
+                    position: actual, ..
+                }) => {
+                    assert_eq!(actual, position, "{text}");
+                },
+                result => panic!("unexpected result {result:?} for {text}"),
+            }
+        }
+    }
+
+    #[test]
+    fn checks_and_evaluates_conditions() {
+        let hierarchy = hierarchy();
+        let index = HierarchyIndex::new(&hierarchy);
+        let condition: Expression =
+            "rising_edge(tb.clk) and tb.valid and tb.ready = 'H' and tb.addr = x\"40\" and tb.state = busy"
+                .parse()
+                .unwrap();
+        let condition = condition.check(&index).unwrap();
+        assert_eq!(condition.element_ids().len(), 12);
+
+        let one = RawValue::from(Value::Logic(Logic::One)).0;
+        let zero = RawValue::from(Value::Logic(Logic::Zero)).0;
+        let high = RawValue::from(Value::Logic(Logic::H)).0;
+        let mut values = Values(HashMap::new());
+        values.set(&index, "tb.clk", 0, 1);
+        values.set(&index, "tb.valid", zero, one);
+        values.set(&index, "tb.ready", high, high);
+        for bit in 0..8 {
+            let value = if bit == 6 { one } else { zero };
+            values.set(&index, &format!("tb.addr({bit})"), value, value);
+        }
+        values.set(&index, "tb.state", 1, 1);
+        assert!(condition.evaluate(&values).unwrap());
+        assert!(condition.fires(&values).unwrap());
+
+        values.set(&index, "tb.clk", 1, 1);
+        assert!(!condition.evaluate(&values).unwrap());
+
+        let check = |text: &str| text.parse::<Expression>().unwrap().check(&index).err();
+        assert!(check("tb.addr(3)'event or tb.addr'event").is_none());
+        assert_eq!(
+            check("tb.addr = \"0\""),
+            Some(ExpressionError::TypeMismatch {
+                left: ExpressionType::Vector(8),
+                right: ExpressionType::Vector(1),
+            }),
+        );
+        assert_eq!(
+            check("tb.state"),
+            Some(ExpressionError::NotBoolean(ExpressionType::Enumeration(
+                vec!["IDLE".into(), "BUSY".into()]
+            ))),
+        );
+        assert_eq!(
+            check("rising_edge(tb.addr)"),
+            Some(ExpressionError::NotLogic(SignalRef::Path("tb.addr".into()))),
+        );
+        assert_eq!(
+            check("tb.state = DONE"),
+            Some(ExpressionError::UnknownSignal(SignalRef::Path(
+                "DONE".into()
+            ))),
+        );
+    }
+
+    #[test]
+    fn rejects_deeply_nested_expressions() {
+        let nested = |depth: usize| {
+            let mut expression = Expression::Literal(Literal::Boolean(true));
+            for _ in 1..depth {
+                expression = Expression::Not(Box::new(expression));
+            }
+            expression
+        };
+        let deepest = nested(MAX_DEPTH);
+        assert_eq!(deepest.depth(), MAX_DEPTH);
+        assert_eq!(deepest.to_string().parse(), Ok(deepest.clone()));
+        let bytes = postcard::to_stdvec(&deepest).unwrap();
+        assert_eq!(postcard::from_bytes(&bytes), Ok(deepest));
+
+        let too_deep = nested(MAX_DEPTH + 1);
+        let hierarchy = hierarchy();
+        let index = HierarchyIndex::new(&hierarchy);
+        assert_eq!(too_deep.check(&index).err(), Some(ExpressionError::TooDeep));
+        let bytes = postcard::to_stdvec(&too_deep).unwrap();
+        assert!(postcard::from_bytes::<Expression>(&bytes).is_err());
+
+        let chain = vec!["tb.valid"; MAX_DEPTH + 1].join(" and ");
+        let parentheses = format!("{}true{}", "(".repeat(1000), ")".repeat(1000));
+        let nots = format!("{}true", "not ".repeat(1000));
+        for text in [chain, parentheses, nots] {
+            assert_eq!(text.parse::<Expression>(), Err(ExpressionError::TooDeep));
+        }
+    }
+
+    #[test]
+    fn displays_extreme_literals_parseably() {
+        for literal in [
+            Literal::Integer(i64::MIN),
+            Literal::Integer(i64::MAX),
+            Literal::Real(f64::INFINITY),
+            Literal::Real(f64::NEG_INFINITY),
+        ] {
+            let expression = Expression::Literal(literal);
+            assert_eq!(expression.to_string().parse(), Ok(expression));
+        }
+        assert!("9223372036854775808".parse::<Expression>().is_err());
+
+        let hierarchy = hierarchy();
+        let index = HierarchyIndex::new(&hierarchy);
+        let nan = Expression::Literal(Literal::Real(f64::NAN));
+        assert_eq!(nan.check(&index).err(), Some(ExpressionError::NotANumber));
+    }
+}
@@ -1,22 +1,195 @@
+use std::error::Error;
+use std::fmt;
 use std::ops::Range;
 
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::SimulationStatus;
+use crate::breakpoint::Breakpoint;
+use crate::breakpoint::BreakpointId;
+use crate::columnar::ColumnarEventsUpdate;
 use crate::design_hierarchy::DesignHierarchy;
 use crate::design_hierarchy::SignalElementId;
+use crate::error::ErrorCode;
+use crate::error::SimulationError;
+use crate::handshake::Welcome;
 use crate::time::LogicalTime;
+use crate::time::PhysicalTime;
+use crate::to_simulator::ForcedValue;
+use crate::to_simulator::RequestId;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum SimulationUpdate {
+    /// Reply to [`Command::Hello`](crate::to_simulator::Command::Hello).
+    Welcome(Welcome),
     SimulationStarted,
-    SimulationPaused,
+    SimulationPaused {
+        time: LogicalTime,
+        reason: PauseReason,
+    },
     SimulationResumed,
     SimulationStopped,
     DesignHierarchy(DesignHierarchy),
     Events(EventsUpdate),
+    /// Same as [`Self::Events`], in the [columnar representation](crate::columnar).
+    ColumnarEvents(ColumnarEventsUpdate),
+    /// Reply to the [request](crate::to_simulator::Request) with the given ID
+    /// if its command has no dedicated reply or failed, see [`RequestId`].
+    CommandResult {
+        request_id: RequestId,
+        result: Result<(), CommandError>,
+    },
+    /// Reply to [`Command::SubscribePattern`](crate::to_simulator::Command::SubscribePattern)
+    /// with the signal elements the pattern resolved to.
+    PatternSubscribed {
+        request_id: RequestId,
+        element_ids: Vec<SignalElementId>,
+    },
+    /// Reply to a stepping command like [`Command::StepDeltaCycle`](crate::to_simulator::Command::StepDeltaCycle)
+    /// with the simulation time after the step.
+    StepCompleted {
+        request_id: RequestId,
+        time: LogicalTime,
+    },
+    /// Reply to [`Command::AddBreakpoint`](crate::to_simulator::Command::AddBreakpoint)
+    /// with the ID assigned to the breakpoint.
+    BreakpointAdded {
+        request_id: RequestId,
+        breakpoint_id: BreakpointId,
+    },
+    /// Reply to [`Command::ListBreakpoints`](crate::to_simulator::Command::ListBreakpoints), ordered by ID.
+    Breakpoints {
+        request_id: RequestId,
+        breakpoints: Vec<(BreakpointId, Breakpoint)>,
+    },
+    /// Reply to [`Command::ListForces`](crate::to_simulator::Command::ListForces).
+    Forces {
+        request_id: RequestId,
+        forces: Vec<ForcedValue>,
+    },
+    /// An error which is not (only) the answer to a single command, e.g. a failed elaboration.
+    Error(SimulationError),
 }
 
+impl SimulationUpdate {
+    /// Returns the ID of the request this update replies to, or `None` if it isn't a reply.
+    pub fn request_id(&self) -> Option<RequestId> {
+        match self {
+            SimulationUpdate::CommandResult { request_id, .. }
+            | SimulationUpdate::PatternSubscribed { request_id, .. }
+            | SimulationUpdate::StepCompleted { request_id, .. }
+            | SimulationUpdate::BreakpointAdded { request_id, .. }
+            | SimulationUpdate::Breakpoints { request_id, .. }
+            | SimulationUpdate::Forces { request_id, .. } => Some(*request_id),
+            SimulationUpdate::Error(error) => error.request_id,
+            _ => None,
+        }
+    }
+}
+
+/// Reason why the simulation was paused.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum PauseReason {
+    /// A client sent [`Command::PauseSimulation`](crate::to_simulator::Command::PauseSimulation).
+    Requested,
+    /// The deadline or duration of [`Command::RunSimulation`](crate::to_simulator::Command::RunSimulation) was reached.
+    DeadlineReached,
+    /// The breakpoint with the given ID fired.
+    ///
+    /// If several breakpoints fire in the same delta cycle, this is the one with the lowest ID.
+    Breakpoint(BreakpointId),
+    /// An assertion or report statement matched the [`ReportBreakpoint`](crate::breakpoint::ReportBreakpoint).
+    Report(Report),
+}
+
+/// Reason why the simulator rejected or failed to execute a command.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum CommandError {
+    /// The simulator does not implement the command.
+    Unsupported,
+    /// The command is not allowed while the simulation has the given status.
+    InvalidState(SimulationStatus),
+    /// The command references signal elements which don't exist in the design hierarchy.
+    UnknownSignals(Vec<SignalElementId>),
+    /// The requested deadline lies before the current simulation time.
+    DeadlineInPast {
+        deadline: PhysicalTime,
+        now: PhysicalTime,
+    },
+    /// The requested time is not a multiple of the simulator's
+    /// [time resolution](crate::design_hierarchy::DesignHierarchy::time_resolution).
+    UnalignedTime {
+        time: PhysicalTime,
+        resolution: PhysicalTime,
+    },
+    /// A signal pattern could not be compiled.
+    InvalidPattern(String),
+    /// The command references a breakpoint which doesn't exist.
+    UnknownBreakpoint(BreakpointId),
+    /// The breakpoint's condition doesn't apply to the signal element.
+    InvalidBreakpoint(String),
+    /// A forced or deposited value doesn't fit the element's type.
+    InvalidValue(String),
+    /// Any other failure, described by a human-readable message.
+    Other(String),
+}
+
+impl CommandError {
+    pub const fn code(&self) -> ErrorCode {
+        match self {
+            CommandError::Unsupported => ErrorCode::Unsupported,
+            CommandError::InvalidState(_) => ErrorCode::InvalidState,
+            CommandError::UnknownSignals(_) => ErrorCode::UnknownSignal,
+            CommandError::DeadlineInPast { .. } => ErrorCode::DeadlineInPast,
+            CommandError::UnalignedTime { .. } => ErrorCode::UnalignedTime,
+            CommandError::InvalidPattern(_) => ErrorCode::InvalidPattern,
+            CommandError::UnknownBreakpoint(_) => ErrorCode::UnknownBreakpoint,
+            CommandError::InvalidBreakpoint(_) => ErrorCode::InvalidBreakpoint,
+            CommandError::InvalidValue(_) => ErrorCode::InvalidValue,
+            CommandError::Other(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Unsupported => formatter.write_str("command is not supported"),
+            CommandError::InvalidState(status) => {
+                write!(
+                    formatter,
+                    "command is not allowed while the simulation is {status:?}"
+                )
+            },
+            CommandError::UnknownSignals(element_ids) => {
+                write!(formatter, "unknown signal elements {element_ids:?}")
+            },
+            CommandError::DeadlineInPast { deadline, now } => {
+                write!(
+                    formatter,
+                    "deadline {deadline:?} lies before the current time {now:?}"
+                )
+            },
+            CommandError::UnalignedTime { time, resolution } => {
+                write!(
+                    formatter,
+                    "time {time} is not a multiple of the time resolution {resolution}"
+                )
+            },
+            CommandError::InvalidPattern(error) => write!(formatter, "invalid pattern: {error}"),
+            CommandError::UnknownBreakpoint(id) => write!(formatter, "unknown {id}"),
+            CommandError::InvalidBreakpoint(error) => {
+                write!(formatter, "invalid breakpoint: {error}")
+            },
+            CommandError::InvalidValue(error) => write!(formatter, "invalid value: {error}"),
+            CommandError::Other(message) => formatter.write_str(message),
+        }
+    }
+}
+
+impl Error for CommandError {}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EventsUpdate {
     pub time_range: Range<LogicalTime>,
@@ -53,7 +226,7 @@ pub struct Event {
 }
 
 /// Raw 64-bit representation of a scalar signal value.
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct RawValue(pub u64);
 
 impl From<f64> for RawValue {
@@ -62,7 +235,7 @@ impl From<f64> for RawValue {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Report {
     pub time: LogicalTime,
     pub message: String,
@@ -72,7 +245,8 @@ pub struct Report {
     pub column: u32,
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+/// Severity level of a [`Report`], ordered from [`Severity::Note`] to [`Severity::Failure`].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Severity {
     Note,
     Warning,
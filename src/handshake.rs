@@ -0,0 +1,316 @@
+//! Version and feature negotiation between a client and a simulator.
+//!
+//! A client opens a connection by sending [`Command::Hello`](crate::to_simulator::Command::Hello).
+//! The simulator answers with [`SimulationUpdate::Welcome`](crate::from_simulator::SimulationUpdate::Welcome),
+//! which contains the protocol version and the feature set both sides agreed on,
+//! and identifies the simulation, e.g. for a client to notice that it reconnected to a different one.
+//! If the peers are incompatible, the simulator instead sends
+//! [`SimulationUpdate::Error`](crate::from_simulator::SimulationUpdate::Error)
+//! with [`ErrorCode::IncompatibleProtocol`](crate::error::ErrorCode::IncompatibleProtocol).
+
+use std::error::Error;
+use std::fmt;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use compact_str::CompactString;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::SimulationId;
+
+/// Version of the protocol spoken by a peer.
+///
+/// Peers with different major versions cannot communicate.
+/// Minor versions only add messages, so a peer can talk to any older minor version
+/// by restricting itself to the lower one.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProtocolVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl ProtocolVersion {
+    /// The protocol version implemented by this crate.
+    pub const CURRENT: Self = Self::new(0, 1);
+
+    pub const fn new(major: u16, minor: u16) -> Self {
+        Self { major, minor }
+    }
+
+    /// Returns whether peers speaking `self` and `other` can communicate.
+    pub const fn is_compatible_with(&self, other: &Self) -> bool {
+        self.major == other.major
+    }
+}
+
+impl Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{major}.{minor}", major = self.major, minor = self.minor)
+    }
+}
+
+impl FromStr for ProtocolVersion {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (major, minor) = value
+            .split_once('.')
+            .ok_or_else(|| format!("protocol version {value:?} is not of the form MAJOR.MINOR"))?;
+        Ok(Self {
+            major: major
+                .parse()
+                .map_err(|error| format!("invalid major version: {error}"))?,
+            minor: minor
+                .parse()
+                .map_err(|error| format!("invalid minor version: {error}"))?,
+        })
+    }
+}
+
+/// An optional protocol feature which must be supported by both peers to be used.
+///
+/// The binary encoding identifies variants by position,
+/// so new features are appended before [`Feature::Unknown`] and existing ones are never reordered.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Feature {
+    /// [`Command::SubscribePattern`](crate::to_simulator::Command::SubscribePattern)
+    PatternSubscription,
+    /// Messages may be sent in the [binary encoding](crate::codec) instead of JSON.
+    BinaryCodec,
+    /// Events may be sent as [`SimulationUpdate::ColumnarEvents`](crate::from_simulator::SimulationUpdate::ColumnarEvents).
+    ColumnarEvents,
+    /// [`Command::StepDeltaCycle`](crate::to_simulator::Command::StepDeltaCycle),
+    /// [`Command::FinishTimeStep`](crate::to_simulator::Command::FinishTimeStep)
+    /// and [`Command::StepToNextEvent`](crate::to_simulator::Command::StepToNextEvent)
+    Stepping,
+    /// [Breakpoints](crate::breakpoint) and their commands
+    Breakpoints,
+    /// [`Breakpoint::Expression`](crate::breakpoint::Breakpoint::Expression)
+    ConditionalBreakpoints,
+    /// [`Command::BreakOnReport`](crate::to_simulator::Command::BreakOnReport)
+    ReportBreakpoints,
+    /// [`Command::Force`](crate::to_simulator::Command::Force), [`Command::Release`](crate::to_simulator::Command::Release),
+    /// [`Command::Deposit`](crate::to_simulator::Command::Deposit) and [`Command::ListForces`](crate::to_simulator::Command::ListForces)
+    Forcing,
+
+    /// A feature this crate version does not know about.
+    ///
+    /// Peers never advertise this themselves; it only appears when deserializing features of a newer peer.
+    #[serde(other)]
+    Unknown,
+}
+
+impl Feature {
+    /// All features implemented by this crate.
+    pub const ALL: &[Self] = &[
+        Feature::PatternSubscription,
+        Feature::BinaryCodec,
+        Feature::ColumnarEvents,
+        Feature::Stepping,
+        Feature::Breakpoints,
+        Feature::ConditionalBreakpoints,
+        Feature::ReportBreakpoints,
+        Feature::Forcing,
+    ];
+}
+
+/// First message sent by a client after connecting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hello {
+    pub protocol_version: ProtocolVersion,
+    /// Features the client is able to use.
+    pub features: Vec<Feature>,
+    /// An optional name of the client application, e.g. for logging.
+    pub client_name: Option<CompactString>,
+}
+
+impl Hello {
+    /// Creates a hello message for the current protocol version and all features of this crate.
+    pub fn new(client_name: Option<CompactString>) -> Self {
+        Self {
+            protocol_version: ProtocolVersion::CURRENT,
+            features: Feature::ALL.to_vec(),
+            client_name,
+        }
+    }
+}
+
+/// Reply of the simulator to a [`Hello`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Welcome {
+    /// The simulation served on the connection, the same as in its
+    /// [marker](crate::server_marker::Marker) and [design hierarchy](crate::design_hierarchy::DesignHierarchy::simulation_id).
+    pub simulation_id: SimulationId,
+    /// The protocol version both peers use for the rest of the connection.
+    pub protocol_version: ProtocolVersion,
+    /// Features supported by both peers.
+    pub features: Vec<Feature>,
+    /// The name of the simulator, e.g. `GHDL`.
+    pub simulator_name: CompactString,
+    /// The version of the simulator.
+    pub simulator_version: CompactString,
+}
+
+impl Welcome {
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+}
+
+/// Reason why a client and a simulator cannot communicate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncompatibilityError {
+    /// The major protocol versions differ.
+    ProtocolVersion {
+        client: ProtocolVersion,
+        simulator: ProtocolVersion,
+    },
+    /// Features required by the simulator are not supported by the client.
+    MissingFeatures(Vec<Feature>),
+}
+
+impl Display for IncompatibilityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncompatibilityError::ProtocolVersion { client, simulator } => write!(
+                f,
+                "client protocol version {client} is incompatible with simulator protocol version {simulator}",
+            ),
+            IncompatibilityError::MissingFeatures(features) => {
+                write!(f, "client does not support required features {features:?}")
+            },
+        }
+    }
+}
+
+impl Error for IncompatibilityError {}
+
+/// Capabilities of a simulator, used to answer a client's [`Hello`].
+#[derive(Debug, Clone)]
+pub struct SimulatorInfo {
+    pub simulator_name: CompactString,
+    pub simulator_version: CompactString,
+    pub protocol_version: ProtocolVersion,
+    /// Features the simulator is able to use.
+    pub supported_features: Vec<Feature>,
+    /// Features without which the simulator refuses to talk to a client.
+    pub required_features: Vec<Feature>,
+}
+
+impl SimulatorInfo {
+    /// Negotiates the protocol version and the common feature set with a client
+    /// of the simulation with the given ID.
+    ///
+    /// The negotiated features keep the simulator's order and never contain [`Feature::Unknown`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the major protocol versions differ,
+    /// or if the client lacks any of the [required features](Self::required_features).
+    pub fn negotiate(
+        &self,
+        simulation_id: SimulationId,
+        hello: &Hello,
+    ) -> Result<Welcome, IncompatibilityError> {
+        if !self
+            .protocol_version
+            .is_compatible_with(&hello.protocol_version)
+        {
+            return Err(IncompatibilityError::ProtocolVersion {
+                client: hello.protocol_version,
+                simulator: self.protocol_version,
+            });
+        }
+
+        let missing_features: Vec<Feature> = self
+            .required_features
+            .iter()
+            .filter(|feature| !hello.features.contains(feature))
+            .copied()
+            .collect();
+        if !missing_features.is_empty() {
+            return Err(IncompatibilityError::MissingFeatures(missing_features));
+        }
+
+        let mut features: Vec<Feature> = vec![];
+        for &feature in &self.supported_features {
+            if feature != Feature::Unknown
+                && hello.features.contains(&feature)
+                && !features.contains(&feature)
+            {
+                features.push(feature);
+            }
+        }
+
+        Ok(Welcome {
+            simulation_id,
+            protocol_version: self.protocol_version.min(hello.protocol_version),
+            features,
+            simulator_name: self.simulator_name.clone(),
+            simulator_version: self.simulator_version.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulator(protocol_version: ProtocolVersion) -> SimulatorInfo {
+        SimulatorInfo {
+            simulator_name: "GHDL".into(),
+            simulator_version: "5.0.0".into(),
+            protocol_version,
+            supported_features: Feature::ALL.to_vec(),
+            required_features: vec![],
+        }
+    }
+
+    #[test]
+    fn negotiates_lower_minor_version() {
+        let hello = Hello {
+            protocol_version: ProtocolVersion::new(0, 7),
+            features: vec![Feature::Unknown],
+            client_name: Some("viewer".into()),
+        };
+        let welcome = simulator(ProtocolVersion::new(0, 3))
+            .negotiate(SimulationId::ZERO, &hello)
+            .unwrap();
+        assert_eq!(welcome.protocol_version, ProtocolVersion::new(0, 3));
+        assert!(!welcome.supports(Feature::Unknown));
+    }
+
+    #[test]
+    fn rejects_different_major_version() {
+        let hello = Hello {
+            protocol_version: ProtocolVersion::new(1, 0),
+            ..Hello::new(None)
+        };
+        assert_eq!(
+            simulator(ProtocolVersion::new(0, 1))
+                .negotiate(SimulationId::ZERO, &hello)
+                .unwrap_err(),
+            IncompatibilityError::ProtocolVersion {
+                client: ProtocolVersion::new(1, 0),
+                simulator: ProtocolVersion::new(0, 1),
+            },
+        );
+    }
+
+    #[test]
+    fn protocol_version_round_trip() {
+        let version = ProtocolVersion::new(2, 13);
+        assert_eq!(version.to_string().parse(), Ok(version));
+        assert!("2".parse::<ProtocolVersion>().is_err());
+    }
+
+    #[test]
+    fn features_keep_their_binary_encoding() {
+        for (position, feature) in Feature::ALL.iter().enumerate() {
+            assert_eq!(postcard::to_stdvec(feature).unwrap(), [position as u8]);
+        }
+        assert_eq!(Feature::ALL[0], Feature::PatternSubscription);
+        assert_eq!(Feature::ALL.last(), Some(&Feature::Forcing));
+    }
+}
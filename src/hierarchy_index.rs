@@ -0,0 +1,302 @@
+//! Fast lookup of signals in a [`DesignHierarchy`] by hierarchical path and by ID.
+//!
+//! Paths join the module names from the root to the signal with dots, e.g. `tb.dut.u_fifo.wr_ptr`.
+//! Unnamed modules don't contribute a path segment,
+//! so their signals and submodules appear directly below the parent module.
+
+use std::collections::HashMap;
+
+use compact_str::CompactString;
+
+use crate::design_hierarchy::DesignHierarchy;
+use crate::design_hierarchy::Module;
+use crate::design_hierarchy::Signal;
+use crate::design_hierarchy::SignalElementId;
+use crate::design_hierarchy::SignalInstanceId;
+
+/// Index over a [`DesignHierarchy`] with constant-time lookups in both directions.
+#[derive(Debug, Clone)]
+pub struct HierarchyIndex<'a> {
+    hierarchy: &'a DesignHierarchy,
+    modules: Vec<ModuleEntry<'a>>,
+    signals: HashMap<SignalInstanceId, SignalEntry<'a>>,
+    /// Maps full signal paths to IDs; the first signal in depth-first order wins for duplicate paths.
+    signal_ids: HashMap<CompactString, SignalInstanceId>,
+}
+
+#[derive(Debug, Clone)]
+struct ModuleEntry<'a> {
+    module: &'a Module,
+    parent: Option<usize>,
+    path: CompactString,
+    depth: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SignalEntry<'a> {
+    signal: &'a Signal,
+    /// Index into [`HierarchyIndex::modules`] of the module declaring the signal.
+    module: usize,
+}
+
+impl<'a> HierarchyIndex<'a> {
+    /// Builds the index in a single pass over the hierarchy.
+    pub fn new(hierarchy: &'a DesignHierarchy) -> Self {
+        let mut index = Self {
+            hierarchy,
+            modules: vec![],
+            signals: HashMap::new(),
+            signal_ids: HashMap::new(),
+        };
+        let mut stack: Vec<(&'a Module, Option<usize>)> = hierarchy
+            .root_modules
+            .iter()
+            .rev()
+            .map(|module| (module, None))
+            .collect();
+        while let Some((module, parent)) = stack.pop() {
+            let parent_entry = parent.map(|parent| &index.modules[parent]);
+            let parent_path = parent_entry.map_or("", |entry| entry.path.as_str());
+            let (path, depth) = match &module.name {
+                Some(name) if parent_path.is_empty() => (name.clone(), 1),
+                Some(name) => (
+                    compact_str::format_compact!("{parent_path}.{name}"),
+                    parent_entry.map_or(0, |entry| entry.depth) + 1,
+                ),
+                None => (
+                    CompactString::from(parent_path),
+                    parent_entry.map_or(0, |entry| entry.depth),
+                ),
+            };
+
+            let module_index = index.modules.len();
+            index.modules.push(ModuleEntry {
+                module,
+                parent,
+                path,
+                depth,
+            });
+            for signal in &module.signals {
+                index.signals.insert(
+                    signal.id,
+                    SignalEntry {
+                        signal,
+                        module: module_index,
+                    },
+                );
+                index
+                    .signal_ids
+                    .entry(index.join_path(module_index, &signal.name).into())
+                    .or_insert(signal.id);
+            }
+            stack.extend(
+                module
+                    .submodules
+                    .iter()
+                    .rev()
+                    .map(|submodule| (submodule, Some(module_index))),
+            );
+        }
+        index
+    }
+
+    pub const fn hierarchy(&self) -> &'a DesignHierarchy {
+        self.hierarchy
+    }
+
+    /// Returns the number of indexed signals.
+    pub fn len(&self) -> usize {
+        self.signals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signals.is_empty()
+    }
+
+    /// Looks up a signal by its hierarchical path, like `tb.dut.u_fifo.wr_ptr`.
+    pub fn signal_id(&self, path: &str) -> Option<SignalInstanceId> {
+        self.signal_ids.get(path).copied()
+    }
+
+    /// Looks up a scalar signal element by its hierarchical path, like `tb.dut.bus(3).payload.valid`.
+    ///
+    /// Paths without an element suffix refer to the element of a scalar signal.
+    pub fn element_id(&self, path: &str) -> Option<SignalElementId> {
+        // the boundary between the signal path and the element path is ambiguous
+        // because record fields also use dots, so try all candidates from the left
+        let boundaries = path
+            .match_indices(['.', '('])
+            .map(|(position, _)| position)
+            .chain([path.len()]);
+        for boundary in boundaries {
+            let (signal_path, element_path) = path.split_at(boundary);
+            let element_id = self.signal_id(signal_path).and_then(|signal_id| {
+                let typ = &self.signals.get(&signal_id)?.signal.typ;
+                let element_index = typ.element_index(&element_path.parse().ok()?)?;
+                Some(SignalElementId::new(signal_id, element_index))
+            });
+            if element_id.is_some() {
+                return element_id;
+            }
+        }
+        None
+    }
+
+    pub fn signal(&self, id: SignalInstanceId) -> Option<&'a Signal> {
+        Some(self.signals.get(&id)?.signal)
+    }
+
+    /// Returns the module in which the signal is declared.
+    pub fn parent_module(&self, id: SignalInstanceId) -> Option<&'a Module> {
+        let entry = self.signals.get(&id)?;
+        Some(self.modules[entry.module].module)
+    }
+
+    /// Returns the full hierarchical path of a signal.
+    pub fn path(&self, id: SignalInstanceId) -> Option<String> {
+        let entry = self.signals.get(&id)?;
+        Some(self.join_path(entry.module, &entry.signal.name))
+    }
+
+    /// Returns the full hierarchical path of a scalar signal element, like `tb.dut.bus(3).payload.valid`.
+    pub fn element_path(&self, id: SignalElementId) -> Option<String> {
+        let entry = self.signals.get(&id.signal_id)?;
+        let element_name = entry.signal.element_name(id.element_index)?;
+        Some(self.join_path(entry.module, &element_name))
+    }
+
+    /// Returns all modules from the root module down to the module declaring the signal.
+    pub fn ancestors(&self, id: SignalInstanceId) -> Option<Vec<&'a Module>> {
+        let mut module = Some(self.signals.get(&id)?.module);
+        let mut ancestors = vec![];
+        while let Some(index) = module {
+            ancestors.push(self.modules[index].module);
+            module = self.modules[index].parent;
+        }
+        ancestors.reverse();
+        Some(ancestors)
+    }
+
+    /// Iterates over all signals in depth-first order.
+    pub fn signals(&self) -> impl Iterator<Item = IndexedSignal<'_, 'a>> {
+        self.modules.iter().flat_map(|entry| {
+            entry.module.signals.iter().map(|signal| IndexedSignal {
+                signal,
+                module: entry.module,
+                module_path: &entry.path,
+                depth: entry.depth + 1,
+            })
+        })
+    }
+
+    fn join_path(&self, module: usize, name: &str) -> String {
+        let module_path = &self.modules[module].path;
+        if module_path.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{module_path}.{name}")
+        }
+    }
+}
+
+/// A signal together with its position in the hierarchy, as returned by [`HierarchyIndex::signals`].
+#[derive(Debug, Clone, Copy)]
+pub struct IndexedSignal<'index, 'a> {
+    pub signal: &'a Signal,
+    /// The module in which the signal is declared.
+    pub module: &'a Module,
+    /// The hierarchical path of [`Self::module`].
+    pub module_path: &'index str,
+    /// The number of path segments of the signal's full path.
+    pub depth: u32,
+}
+
+impl IndexedSignal<'_, '_> {
+    /// Returns the full hierarchical path of the signal.
+    pub fn path(&self) -> String {
+        self.path_chars().collect()
+    }
+
+    /// Returns the characters of the [path](Self::path) without allocating it.
+    pub fn path_chars(&self) -> impl Iterator<Item = char> {
+        let separator = (!self.module_path.is_empty()).then_some('.');
+        self.module_path
+            .chars()
+            .chain(separator)
+            .chain(self.signal.name.chars())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::design_hierarchy::ModuleKind;
+    use crate::design_hierarchy::SignalType;
+    use crate::test_utils::hierarchy;
+    use crate::test_utils::logic_vector;
+    use crate::test_utils::module;
+    use crate::test_utils::signal;
+
+    #[test]
+    fn looks_up_signals_by_path_and_id() {
+        let fifo = module(
+            "u_fifo",
+            vec![],
+            vec![signal("wr_ptr", 3, SignalType::Logic)],
+        );
+        let dut = module(
+            "dut",
+            vec![fifo],
+            vec![signal("bus", 2, logic_vector(3, 0))],
+        );
+        let hierarchy = hierarchy(vec![module(
+            "tb",
+            vec![dut],
+            vec![signal("clk", 1, SignalType::Bit)],
+        )]);
+        let index = HierarchyIndex::new(&hierarchy);
+
+        let wr_ptr = index.signal_id("tb.dut.u_fifo.wr_ptr").unwrap();
+        assert_eq!(wr_ptr.0.get(), 3);
+        assert_eq!(index.path(wr_ptr).unwrap(), "tb.dut.u_fifo.wr_ptr");
+        assert_eq!(
+            index.parent_module(wr_ptr).unwrap().name.as_deref(),
+            Some("u_fifo")
+        );
+        assert_eq!(index.ancestors(wr_ptr).unwrap().len(), 3);
+        assert!(index.signal_id("tb.dut.wr_ptr").is_none());
+
+        let bit = index.element_id("tb.dut.bus(1)").unwrap();
+        assert_eq!(bit.element_index, 2);
+        assert_eq!(index.element_path(bit).unwrap(), "tb.dut.bus(1)");
+        assert_eq!(index.element_id("tb.clk").unwrap().element_index, 0);
+        assert_eq!(index.signals().count(), 3);
+        let paths: Vec<String> = index.signals().map(|signal| signal.path()).collect();
+        assert_eq!(paths, ["tb.clk", "tb.dut.bus", "tb.dut.u_fifo.wr_ptr"]);
+    }
+
+    #[test]
+    fn finds_signals_of_unnamed_and_duplicate_modules() {
+        let unnamed = Module {
+            name: None,
+            kind: ModuleKind::Package,
+            submodules: vec![],
+            signals: vec![signal("hidden", 2, SignalType::Bit)],
+        };
+        let hierarchy = hierarchy(vec![
+            module(
+                "pkg",
+                vec![unnamed],
+                vec![signal("first", 1, SignalType::Bit)],
+            ),
+            module("pkg", vec![], vec![signal("second", 3, SignalType::Bit)]),
+        ]);
+        let index = HierarchyIndex::new(&hierarchy);
+
+        let hidden = index.signal_id("pkg.hidden").unwrap();
+        assert_eq!(hidden.0.get(), 2);
+        assert_eq!(index.path(hidden).unwrap(), "pkg.hidden");
+        assert_eq!(index.signal_id("pkg.first").unwrap().0.get(), 1);
+        assert_eq!(index.signal_id("pkg.second").unwrap().0.get(), 3);
+    }
+}
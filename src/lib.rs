@@ -1,9 +1,27 @@
+pub mod breakpoint;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod codec;
+pub mod columnar;
 pub mod design_hierarchy;
+pub mod discovery;
+pub mod element_path;
+pub mod error;
+pub mod expression;
 pub mod from_simulator;
+pub mod handshake;
+pub mod hierarchy_index;
 pub mod serde_utils;
+#[cfg(feature = "server")]
+pub mod server;
 pub mod server_marker;
+pub mod signal_pattern;
+#[cfg(test)]
+mod test_utils;
 pub mod time;
 pub mod to_simulator;
+pub mod value;
+pub mod vcd;
 
 use std::fmt;
 use std::fmt::Display;
@@ -0,0 +1,596 @@
+//! WebSocket server for simulator adapters, available with the `server` cargo feature.
+//!
+//! [`Server::bind`] listens on an ephemeral localhost port and advertises it with a marker file,
+//! which is removed when the server is dropped.
+//! The server answers each client's [`Hello`](crate::handshake::Hello) based on a [`SimulatorInfo`],
+//! tracks the signal elements every client subscribed to,
+//! and hands all other requests to the adapter as [`ServerEvent`]s.
+//!
+//! Updates are sent to a client as JSON text messages until it negotiated [`Feature::BinaryCodec`],
+//! and in the [binary encoding](crate::codec) afterwards.
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::time::Duration;
+
+use futures_util::SinkExt;
+use futures_util::StreamExt;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::SimulationId;
+use crate::codec;
+use crate::columnar::ColumnarEventsUpdate;
+use crate::design_hierarchy::SignalElementId;
+use crate::error::ErrorCode;
+use crate::error::SimulationError;
+use crate::from_simulator::EventsUpdate;
+use crate::from_simulator::SimulationUpdate;
+use crate::handshake::Feature;
+use crate::handshake::SimulatorInfo;
+use crate::handshake::Welcome;
+use crate::server_marker::Marker;
+use crate::server_marker::MarkerGuard;
+use crate::server_marker::MarkerMetadata;
+use crate::server_marker::create_markers_directory;
+use crate::to_simulator::Command;
+use crate::to_simulator::Request;
+
+/// Delay before accepting again after accepting a connection failed, doubled after every further failure.
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(10);
+const MAX_ACCEPT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Identifies a client connection of a [`Server`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClientId(pub u32);
+
+impl fmt::Display for ClientId {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "client {}", self.0)
+    }
+}
+
+/// Something the adapter has to react to.
+#[derive(Debug)]
+pub enum ServerEvent {
+    /// A client completed the handshake.
+    Connected(ClientId),
+    /// A client sent a request other than [`Command::Hello`].
+    ///
+    /// Subscriptions are already recorded by the server,
+    /// but the adapter still has to validate them and reply with a [`SimulationUpdate::CommandResult`].
+    Request { client: ClientId, request: Request },
+    /// A client which completed the handshake disconnected.
+    Disconnected(ClientId),
+}
+
+/// Serves WebSocket clients on behalf of a simulator adapter.
+#[derive(Debug)]
+pub struct Server {
+    marker: MarkerGuard,
+    clients: Arc<Clients>,
+    events: mpsc::UnboundedReceiver<ServerEvent>,
+    accept_task: JoinHandle<()>,
+}
+
+impl Server {
+    /// Starts listening on an ephemeral port on `127.0.0.1` and creates the marker file for the simulation.
+    ///
+    /// Must be called inside a Tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if binding the port or creating the marker file fails.
+    pub async fn bind(simulation_id: SimulationId, info: SimulatorInfo) -> io::Result<Self> {
+        Self::bind_in(&create_markers_directory()?, simulation_id, info).await
+    }
+
+    /// Same as [`bind`](Self::bind), but creates the marker file in `markers_directory`
+    /// instead of the [markers directory](crate::server_marker::markers_directory).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if binding the port or creating the marker file fails.
+    pub async fn bind_in(
+        markers_directory: &Path,
+        simulation_id: SimulationId,
+        info: SimulatorInfo,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let marker = Marker {
+            port: listener.local_addr()?.port(),
+            simulation_id,
+        };
+        let marker = MarkerGuard::create_in(
+            markers_directory,
+            marker,
+            &MarkerMetadata::for_current_process(),
+        )?;
+
+        let (event_sender, events) = mpsc::unbounded_channel();
+        let clients = Arc::new(Clients {
+            simulation_id,
+            info,
+            events: event_sender,
+            clients: Mutex::default(),
+        });
+        let accept_task = tokio::spawn(accept_clients(listener, Arc::clone(&clients)));
+
+        Ok(Self {
+            marker,
+            clients,
+            events,
+            accept_task,
+        })
+    }
+
+    pub const fn marker(&self) -> Marker {
+        self.marker.marker()
+    }
+
+    /// Replaces the metadata in the marker file, e.g. once the design is elaborated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the marker file can't be written.
+    pub fn update_marker_metadata(&self, metadata: &MarkerMetadata) -> io::Result<()> {
+        self.marker.update_metadata(metadata)
+    }
+
+    /// Waits for the next event from any client.
+    pub async fn next_event(&mut self) -> Option<ServerEvent> {
+        self.events.recv().await
+    }
+
+    /// Returns the clients which completed the handshake.
+    pub fn clients(&self) -> Vec<ClientId> {
+        self.clients
+            .lock()
+            .iter()
+            .filter(|(_, client)| client.welcome.is_some())
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    /// Returns the signal elements subscribed by any client.
+    pub fn subscribed_elements(&self) -> BTreeSet<SignalElementId> {
+        self.clients
+            .lock()
+            .values()
+            .flat_map(|client| client.subscriptions.iter().copied())
+            .collect()
+    }
+
+    /// Sends an update to a single client without filtering it.
+    ///
+    /// Elements in a [`SimulationUpdate::PatternSubscribed`] are added to the client's subscriptions.
+    /// Returns `false` if the client is not connected or hasn't completed the handshake.
+    pub fn send(&self, client: ClientId, update: SimulationUpdate) -> bool {
+        let mut clients = self.clients.lock();
+        let Some(handle) = clients
+            .get_mut(&client)
+            .filter(|handle| handle.welcome.is_some())
+        else {
+            return false;
+        };
+        if let SimulationUpdate::PatternSubscribed { element_ids, .. } = &update {
+            handle.subscriptions.extend(element_ids);
+        }
+        handle.updates.send(update).is_ok()
+    }
+
+    /// Sends an update to all clients which completed the handshake.
+    ///
+    /// Events are restricted to the signal elements each client subscribed to,
+    /// and converted to the [columnar representation](crate::columnar) for clients which negotiated [`Feature::ColumnarEvents`].
+    pub fn broadcast(&self, update: &SimulationUpdate) {
+        for handle in self.clients.lock().values() {
+            let Some(welcome) = &handle.welcome else {
+                continue;
+            };
+            let update = match update {
+                SimulationUpdate::Events(events) => {
+                    let events = handle.filter_events(events);
+                    let columnar = welcome
+                        .supports(Feature::ColumnarEvents)
+                        .then(|| ColumnarEventsUpdate::try_from(&events).ok())
+                        .flatten();
+                    // events which don't fit the columnar representation are sent as they are
+                    match columnar {
+                        Some(columnar) => SimulationUpdate::ColumnarEvents(columnar),
+                        None => SimulationUpdate::Events(events),
+                    }
+                },
+                SimulationUpdate::ColumnarEvents(events) => {
+                    let mut events = events.clone();
+                    events
+                        .signals
+                        .retain(|signal| handle.subscriptions.contains(&signal.element_id));
+                    SimulationUpdate::ColumnarEvents(events)
+                },
+                update => update.clone(),
+            };
+            // a disconnected client is removed by its task
+            let _ = handle.updates.send(update);
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+        for (_, handle) in self.clients.lock().drain() {
+            handle.task.abort();
+        }
+    }
+}
+
+/// State shared between the server and the client tasks.
+#[derive(Debug)]
+struct Clients {
+    simulation_id: SimulationId,
+    info: SimulatorInfo,
+    events: mpsc::UnboundedSender<ServerEvent>,
+    clients: Mutex<HashMap<ClientId, ClientHandle>>,
+}
+
+impl Clients {
+    fn lock(&self) -> MutexGuard<'_, HashMap<ClientId, ClientHandle>> {
+        // the map is never left in an inconsistent state
+        self.clients
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[derive(Debug)]
+struct ClientHandle {
+    updates: mpsc::UnboundedSender<SimulationUpdate>,
+    /// The negotiated session, or `None` before the handshake.
+    welcome: Option<Welcome>,
+    subscriptions: BTreeSet<SignalElementId>,
+    task: AbortHandle,
+}
+
+impl ClientHandle {
+    fn filter_events(&self, events: &EventsUpdate) -> EventsUpdate {
+        EventsUpdate {
+            time_range: events.time_range.clone(),
+            signals: events
+                .signals
+                .iter()
+                .filter(|signal| self.subscriptions.contains(&signal.element_id))
+                .cloned()
+                .collect(),
+            reports: events.reports.clone(),
+        }
+    }
+}
+
+async fn accept_clients(listener: TcpListener, clients: Arc<Clients>) {
+    let mut next_id = 0;
+    let mut retry_delay = ACCEPT_RETRY_DELAY;
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            // errors like running out of file descriptors persist for a while, don't spin
+            tokio::time::sleep(retry_delay).await;
+            retry_delay = (retry_delay * 2).min(MAX_ACCEPT_RETRY_DELAY);
+            continue;
+        };
+        retry_delay = ACCEPT_RETRY_DELAY;
+        let id = ClientId(next_id);
+        next_id = next_id.wrapping_add(1);
+
+        let (updates, update_receiver) = mpsc::unbounded_channel();
+        // hold the lock until the handle is inserted, so the task can't look it up too early
+        let mut map = clients.lock();
+        let task = tokio::spawn(serve_client(
+            id,
+            stream,
+            Arc::clone(&clients),
+            update_receiver,
+        ));
+        map.insert(
+            id,
+            ClientHandle {
+                updates,
+                welcome: None,
+                subscriptions: BTreeSet::new(),
+                task: task.abort_handle(),
+            },
+        );
+    }
+}
+
+async fn serve_client(
+    id: ClientId,
+    stream: TcpStream,
+    clients: Arc<Clients>,
+    mut updates: mpsc::UnboundedReceiver<SimulationUpdate>,
+) {
+    let mut connected = false;
+    if let Ok(socket) = tokio_tungstenite::accept_async(stream).await {
+        let (mut sink, mut incoming) = socket.split();
+        let mut binary = false;
+        loop {
+            let reply = tokio::select! {
+                update = updates.recv() => match update {
+                    Some(update) => update,
+                    None => break,
+                },
+                message = incoming.next() => {
+                    let request = match message {
+                        Some(Ok(Message::Text(text))) => {
+                            serde_json::from_str::<Request>(&text).map_err(|error| error.to_string())
+                        },
+                        Some(Ok(Message::Binary(bytes))) => {
+                            codec::decode::<Request>(&bytes).map_err(|error| error.to_string())
+                        },
+                        Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                        Some(Ok(_)) => continue,
+                    };
+                    match request {
+                        Ok(request) => match handle_request(id, &clients, request, connected) {
+                            Ok(Some(welcome)) => {
+                                connected = true;
+                                let reply = SimulationUpdate::Welcome(welcome.clone());
+                                if send_update(&mut sink, &reply, binary).await.is_err() {
+                                    break;
+                                }
+                                binary = welcome.supports(Feature::BinaryCodec);
+                                let _ = clients.events.send(ServerEvent::Connected(id));
+                                continue;
+                            },
+                            Ok(None) => continue,
+                            Err(error) => error.into(),
+                        },
+                        Err(message) => SimulationError::new(ErrorCode::MalformedMessage, message).into(),
+                    }
+                },
+            };
+            if send_update(&mut sink, &reply, binary).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    clients.lock().remove(&id);
+    if connected {
+        let _ = clients.events.send(ServerEvent::Disconnected(id));
+    }
+}
+
+/// Handles a request from a client.
+///
+/// Returns the [`Welcome`] if the request completed the handshake.
+fn handle_request(
+    id: ClientId,
+    clients: &Clients,
+    request: Request,
+    connected: bool,
+) -> Result<Option<Welcome>, SimulationError> {
+    let mut map = clients.lock();
+    let Some(handle) = map.get_mut(&id) else {
+        return Ok(None);
+    };
+    match request.command {
+        Command::Hello(_) if connected => Err(SimulationError::new(
+            ErrorCode::InvalidState,
+            "the session is already open",
+        )
+        .for_request(request.id)),
+        Command::Hello(hello) => {
+            let welcome = clients
+                .info
+                .negotiate(clients.simulation_id, &hello)
+                .map_err(|error| SimulationError::from(error).for_request(request.id))?;
+            handle.welcome = Some(welcome.clone());
+            Ok(Some(welcome))
+        },
+        _ if !connected => Err(SimulationError::new(
+            ErrorCode::InvalidState,
+            "the session must be opened with a Hello",
+        )
+        .for_request(request.id)),
+        command => {
+            match &command {
+                Command::Subscribe(element_ids) => handle.subscriptions.extend(element_ids),
+                Command::Unsubscribe(element_ids) => {
+                    for element_id in element_ids {
+                        handle.subscriptions.remove(element_id);
+                    }
+                },
+                _ => {},
+            }
+            let request = Request::new(request.id, command);
+            let _ = clients.events.send(ServerEvent::Request {
+                client: id,
+                request,
+            });
+            Ok(None)
+        },
+    }
+}
+
+async fn send_update(
+    sink: &mut (impl SinkExt<Message, Error = tungstenite::Error> + Unpin),
+    update: &SimulationUpdate,
+    binary: bool,
+) -> Result<(), ()> {
+    let message = if binary {
+        Message::Binary(codec::encode(update).map_err(drop)?.into())
+    } else {
+        Message::Text(serde_json::to_string(update).map_err(drop)?.into())
+    };
+    sink.send(message).await.map_err(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::num::NonZeroU32;
+
+    use tokio_tungstenite::MaybeTlsStream;
+    use tokio_tungstenite::WebSocketStream;
+
+    use super::*;
+    use crate::design_hierarchy::SignalInstanceId;
+    use crate::discovery::discover_in;
+    use crate::from_simulator::PauseReason;
+    use crate::from_simulator::SignalEvents;
+    use crate::handshake::Hello;
+    use crate::handshake::ProtocolVersion;
+    use crate::test_utils::temp_directory;
+    use crate::time::LogicalTime;
+    use crate::to_simulator::RequestId;
+
+    type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    async fn send(socket: &mut Socket, command: Command) {
+        let text = serde_json::to_string(&Request::new(RequestId(0), command)).unwrap();
+        socket.send(Message::Text(text.into())).await.unwrap();
+    }
+
+    async fn receive(socket: &mut Socket) -> SimulationUpdate {
+        match socket.next().await.unwrap().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            message => panic!("unexpected message {message:?}"),
+        }
+    }
+
+    async fn bind(markers_directory: &Path) -> Server {
+        let info = SimulatorInfo {
+            simulator_name: "test".into(),
+            simulator_version: "1.0".into(),
+            protocol_version: ProtocolVersion::CURRENT,
+            supported_features: vec![],
+            required_features: vec![],
+        };
+        Server::bind_in(markers_directory, SimulationId::new_random(), info)
+            .await
+            .unwrap()
+    }
+
+    fn is_invalid_state(update: &SimulationUpdate) -> bool {
+        matches!(
+            update,
+            SimulationUpdate::Error(SimulationError {
+                code: ErrorCode::InvalidState,
+                ..
+            })
+        )
+    }
+
+    #[tokio::test]
+    async fn filters_events_per_client() {
+        let directory = temp_directory();
+        let mut server = bind(&directory).await;
+        let marker = server.marker();
+        let markers = discover_in([&directory]);
+        assert_eq!(markers.len(), 1);
+        assert_eq!(markers[0].marker, marker);
+
+        let element_ids = [1, 2].map(|index| {
+            SignalElementId::new_scalar(SignalInstanceId(NonZeroU32::new(index).unwrap()))
+        });
+        let url = format!("ws://127.0.0.1:{port}", port = marker.port);
+        let mut sockets = vec![];
+        for element_id in element_ids {
+            let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+            send(&mut socket, Command::PauseSimulation).await;
+            assert!(is_invalid_state(&receive(&mut socket).await));
+
+            send(&mut socket, Command::Hello(Hello::new(None))).await;
+            assert!(matches!(
+                receive(&mut socket).await,
+                SimulationUpdate::Welcome(_)
+            ));
+            assert!(matches!(
+                server.next_event().await,
+                Some(ServerEvent::Connected(_))
+            ));
+
+            send(&mut socket, Command::Subscribe(vec![element_id])).await;
+            assert!(matches!(
+                server.next_event().await,
+                Some(ServerEvent::Request {
+                    request: Request {
+                        command: Command::Subscribe(_),
+                        ..
+                    },
+                    ..
+                })
+            ));
+            sockets.push(socket);
+        }
+        assert_eq!(server.subscribed_elements().len(), 2);
+
+        server.broadcast(&SimulationUpdate::Events(EventsUpdate {
+            time_range: LogicalTime::ZERO..LogicalTime::ZERO,
+            signals: element_ids.map(SignalEvents::new).to_vec(),
+            reports: vec![],
+        }));
+        for (socket, element_id) in sockets.iter_mut().zip(element_ids) {
+            let SimulationUpdate::Events(events) = receive(socket).await else {
+                panic!("expected events");
+            };
+            assert_eq!(events.signals.len(), 1);
+            assert_eq!(events.signals[0].element_id, element_id);
+        }
+
+        drop(sockets.pop());
+        assert!(matches!(
+            server.next_event().await,
+            Some(ServerEvent::Disconnected(_))
+        ));
+        drop(server);
+        assert!(discover_in([&directory]).is_empty());
+        fs::remove_dir(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn only_serves_clients_after_the_handshake() {
+        let directory = temp_directory();
+        let mut server = bind(&directory).await;
+        let url = format!("ws://127.0.0.1:{port}", port = server.marker().port);
+        let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        assert!(!server.send(ClientId(0), SimulationUpdate::SimulationStarted));
+        server.broadcast(&SimulationUpdate::SimulationStarted);
+
+        send(&mut socket, Command::Hello(Hello::new(None))).await;
+        assert!(matches!(
+            receive(&mut socket).await,
+            SimulationUpdate::Welcome(welcome) if welcome.simulation_id == server.marker().simulation_id
+        ));
+        let Some(ServerEvent::Connected(client)) = server.next_event().await else {
+            panic!("expected a connected client");
+        };
+        assert!(server.send(
+            client,
+            SimulationUpdate::SimulationPaused {
+                time: LogicalTime::ZERO,
+                reason: PauseReason::Requested,
+            }
+        ));
+        assert!(matches!(
+            receive(&mut socket).await,
+            SimulationUpdate::SimulationPaused { .. }
+        ));
+
+        send(&mut socket, Command::Hello(Hello::new(None))).await;
+        assert!(is_invalid_state(&receive(&mut socket).await));
+        drop(server);
+        fs::remove_dir(directory).unwrap();
+    }
+}
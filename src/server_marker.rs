@@ -1,23 +1,135 @@
-//! Empty marker files under the OS temp directory advertise running GHDL adapter WebSocket ports.
+//! Marker files under the OS temp directory advertise running GHDL adapter WebSocket ports.
+//!
+//! A marker file contains [`MarkerMetadata`] as `key=value` lines, e.g. `pid=1234`.
+//! Empty marker files written by older adapters are still recognized.
 
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::net::TcpStream;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use crate::SimulationId;
+use crate::handshake::ProtocolVersion;
 
 /// Subdirectory of [`std::env::temp_dir()`] where marker files are stored.
 pub const SERVER_MARKER_SUBDIR: &str = "hdl-sim";
 
+/// Environment variable overriding the [markers directory](markers_directory).
+pub const MARKERS_DIRECTORY_ENV: &str = "HDL_SIM_MARKERS_DIR";
+
+/// Environment variable selecting the [per-user markers directory](user_markers_directory),
+/// unless empty or `0`.
+pub const PER_USER_MARKERS_ENV: &str = "HDL_SIM_PER_USER_MARKERS";
+
 /// Filename suffix for a marker file (`{port}-{id}.server` with 14-digit lowercase hex `id`).
 pub const SERVER_MARKER_SUFFIX: &str = ".server";
 
 /// Width of the hexadecimal simulation instance id in marker file names (covers 53 bits).
 pub const SIMULATION_ID_HEX_DIGITS: usize = 14;
 
+/// How long [`is_stale`] waits for a connection to a marker's port.
+pub const PROBE_TIMEOUT: Duration = Duration::from_millis(200);
+
 /// Returns the directory containing marker files.
+///
+/// This is the directory named by [`MARKERS_DIRECTORY_ENV`] if set,
+/// the [per-user directory](user_markers_directory) if [`PER_USER_MARKERS_ENV`] is set,
+/// and the [shared directory](shared_markers_directory) otherwise.
 #[must_use]
 pub fn markers_directory() -> PathBuf {
-    std::env::temp_dir().join(SERVER_MARKER_SUBDIR)
+    if let Some(directory) = env::var_os(MARKERS_DIRECTORY_ENV).filter(|value| !value.is_empty()) {
+        return PathBuf::from(directory);
+    }
+    if is_per_user() {
+        user_markers_directory()
+    } else {
+        shared_markers_directory()
+    }
+}
+
+/// Returns the markers directory shared by all users, `{temp_dir}/hdl-sim`.
+#[must_use]
+pub fn shared_markers_directory() -> PathBuf {
+    env::temp_dir().join(SERVER_MARKER_SUBDIR)
+}
+
+/// Returns the markers directory of the current user, `{temp_dir}/hdl-sim-{user}`.
+///
+/// It is created only accessible by the user.
+#[must_use]
+pub fn user_markers_directory() -> PathBuf {
+    let user = env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_default();
+    let mut user: String = user
+        .chars()
+        .filter(|&char| char.is_ascii_alphanumeric() || matches!(char, '.' | '_' | '-'))
+        .collect();
+    if user.is_empty() {
+        user = fallback_user_name();
+    }
+    env::temp_dir().join(format!("{SERVER_MARKER_SUBDIR}-{user}"))
+}
+
+/// Creates the [markers directory](markers_directory) if it doesn't exist and returns its path.
+///
+/// The per-user directory is restricted to its owner on Unix.
+///
+/// # Errors
+///
+/// Returns an error if the directory can't be created, or the per-user directory belongs to another user.
+pub fn create_markers_directory() -> io::Result<PathBuf> {
+    let directory = markers_directory();
+    if directory == user_markers_directory() {
+        create_private_directory(&directory)?;
+    } else {
+        fs::create_dir_all(&directory)?;
+    }
+    Ok(directory)
+}
+
+fn is_per_user() -> bool {
+    env::var_os(PER_USER_MARKERS_ENV).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Identifies the user if the user name is unknown.
+fn fallback_user_name() -> String {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        // the process directory is owned by the process' user
+        if let Ok(metadata) = fs::metadata("/proc/self") {
+            return format!("uid{}", metadata.uid());
+        }
+    }
+    "default".into()
+}
+
+fn create_private_directory(directory: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::fs::Permissions;
+        use std::os::unix::fs::DirBuilderExt;
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(directory)?;
+        // fails if the directory was created by another user
+        fs::set_permissions(directory, Permissions::from_mode(0o700))
+    }
+    #[cfg(not(unix))]
+    {
+        fs::create_dir_all(directory)
+    }
 }
 
 /// Returns the path to the marker file for `port` and `simulation_id`.
@@ -25,11 +137,15 @@ pub fn markers_directory() -> PathBuf {
 /// File name format: `{port}-{simulation_id:014x}.server` (lowercase hex).
 #[must_use]
 pub fn marker_path(port: u16, simulation_id: SimulationId) -> PathBuf {
-    markers_directory().join(format!("{port}-{simulation_id}{SERVER_MARKER_SUFFIX}"))
+    markers_directory().join(marker_file_name(port, simulation_id))
+}
+
+fn marker_file_name(port: u16, simulation_id: SimulationId) -> String {
+    format!("{port}-{simulation_id}{SERVER_MARKER_SUFFIX}")
 }
 
 /// Information from simulation marker file.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Marker {
     pub port: u16,
     pub simulation_id: SimulationId,
@@ -53,11 +169,255 @@ impl Marker {
             simulation_id,
         })
     }
+
+    /// Reads the metadata from the marker file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the marker file can't be read.
+    pub fn metadata(&self) -> io::Result<MarkerMetadata> {
+        MarkerMetadata::read(&marker_path(self.port, self.simulation_id))
+    }
+}
+
+/// Information about a simulation stored in its marker file,
+/// so it can be shown without connecting to the simulation.
+///
+/// All fields are optional; unknown keys and unparsable values are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarkerMetadata {
+    /// ID of the adapter process.
+    pub pid: Option<u32>,
+    pub hostname: Option<String>,
+    /// Name of the user running the simulation.
+    pub user: Option<String>,
+    pub top_level_entity: Option<String>,
+    /// Same as [`DesignHierarchy::name`](crate::design_hierarchy::DesignHierarchy::name).
+    pub design_name: Option<String>,
+    /// Same as [`DesignHierarchy::start_time`](crate::design_hierarchy::DesignHierarchy::start_time).
+    pub start_time: Option<f64>,
+    pub protocol_version: Option<ProtocolVersion>,
+}
+
+impl MarkerMetadata {
+    /// Returns the metadata known without a design: process ID, hostname, user and protocol version.
+    pub fn for_current_process() -> Self {
+        let user = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .ok();
+        Self {
+            pid: Some(std::process::id()),
+            hostname: current_hostname(),
+            user,
+            protocol_version: Some(ProtocolVersion::CURRENT),
+            ..Self::default()
+        }
+    }
+
+    /// Parses the contents of a marker file.
+    pub fn parse(contents: &str) -> Self {
+        let mut metadata = Self::default();
+        for (key, value) in contents.lines().filter_map(|line| line.split_once('=')) {
+            let value = value.trim();
+            let text = || Some(value.to_owned());
+            match key.trim() {
+                "pid" => metadata.pid = value.parse().ok(),
+                "hostname" => metadata.hostname = text(),
+                "user" => metadata.user = text(),
+                "top_level_entity" => metadata.top_level_entity = text(),
+                "design_name" => metadata.design_name = text(),
+                "start_time" => metadata.start_time = value.parse().ok(),
+                "protocol_version" => metadata.protocol_version = value.parse().ok(),
+                _ => {},
+            }
+        }
+        metadata
+    }
+
+    /// Reads the marker file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        fs::read_to_string(path).map(|contents| Self::parse(&contents))
+    }
+}
+
+/// Formats the metadata as marker file contents.
+impl fmt::Display for MarkerMetadata {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = [
+            ("pid", self.pid.map(|pid| pid.to_string())),
+            ("hostname", self.hostname.clone()),
+            ("user", self.user.clone()),
+            ("top_level_entity", self.top_level_entity.clone()),
+            ("design_name", self.design_name.clone()),
+            ("start_time", self.start_time.map(|time| time.to_string())),
+            (
+                "protocol_version",
+                self.protocol_version.map(|version| version.to_string()),
+            ),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                // line breaks would end the value early
+                writeln!(formatter, "{key}={}", value.replace(['\n', '\r'], " "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Marker file which is removed when the guard is dropped.
+#[derive(Debug)]
+pub struct MarkerGuard {
+    marker: Marker,
+    path: PathBuf,
+}
+
+impl MarkerGuard {
+    /// Creates the marker file, and the markers directory if necessary,
+    /// with the [metadata of the current process](MarkerMetadata::for_current_process).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or the file can't be created.
+    pub fn create(marker: Marker) -> io::Result<Self> {
+        Self::create_with_metadata(marker, &MarkerMetadata::for_current_process())
+    }
+
+    /// Creates the marker file, and the markers directory if necessary, with the given metadata.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or the file can't be created.
+    pub fn create_with_metadata(marker: Marker, metadata: &MarkerMetadata) -> io::Result<Self> {
+        let directory = create_markers_directory()?;
+        Self::create_in(&directory, marker, metadata)
+    }
+
+    /// Creates the marker file with the given metadata in `directory` instead of the
+    /// [markers directory](markers_directory), e.g. one that isn't shared with other simulations.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be created, e.g. because `directory` doesn't exist.
+    pub fn create_in(
+        directory: &Path,
+        marker: Marker,
+        metadata: &MarkerMetadata,
+    ) -> io::Result<Self> {
+        let path = directory.join(marker_file_name(marker.port, marker.simulation_id));
+        fs::write(&path, metadata.to_string())?;
+        Ok(Self { marker, path })
+    }
+
+    /// Replaces the metadata in the marker file, e.g. once the design is elaborated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn update_metadata(&self, metadata: &MarkerMetadata) -> io::Result<()> {
+        fs::write(&self.path, metadata.to_string())
+    }
+
+    pub const fn marker(&self) -> Marker {
+        self.marker
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for MarkerGuard {
+    fn drop(&mut self) {
+        // the marker may already have been removed by a sweep
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Returns `true` if the marker file at `path` doesn't belong to a running simulation,
+/// i.e. the recorded process has exited or nothing accepts connections on the marker's port.
+///
+/// Markers written on another host, e.g. into a shared temp directory, are never stale,
+/// because neither their process nor their port can be checked from here.
+pub fn is_stale(path: &Path, marker: Marker) -> bool {
+    let metadata = MarkerMetadata::read(path).unwrap_or_default();
+    if let (Some(hostname), Some(current)) = (&metadata.hostname, current_hostname())
+        && *hostname != current
+    {
+        return false;
+    }
+    if metadata.pid.and_then(process_exists) == Some(false) {
+        return true;
+    }
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, marker.port));
+    TcpStream::connect_timeout(&address, PROBE_TIMEOUT).is_err()
+}
+
+/// Removes all [stale](is_stale) marker files from the [markers directory](markers_directory).
+///
+/// Returns the removed markers.
+///
+/// # Errors
+///
+/// Returns an error if the directory can't be read or a marker file can't be removed.
+pub fn remove_stale_markers() -> io::Result<Vec<Marker>> {
+    let entries = match fs::read_dir(markers_directory()) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(error) => return Err(error),
+    };
+
+    let mut removed = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        let Some(marker) = Marker::try_from_path(&path) else {
+            continue;
+        };
+        if is_stale(&path, marker) {
+            match fs::remove_file(&path) {
+                Ok(()) => removed.push(marker),
+                // removed concurrently, e.g. by the simulation itself
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {},
+                Err(error) => return Err(error),
+            }
+        }
+    }
+    Ok(removed)
+}
+
+/// Returns the name of this host, or `None` if it's unknown.
+fn current_hostname() -> Option<String> {
+    env::var("HOSTNAME")
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| {
+            let hostname = fs::read_to_string("/etc/hostname").ok()?;
+            Some(hostname.trim().to_owned())
+        })
+        .filter(|hostname| !hostname.is_empty())
+}
+
+/// Returns whether a process with the given ID exists, or `None` if this can't be determined.
+fn process_exists(pid: u32) -> Option<bool> {
+    #[cfg(target_os = "linux")]
+    {
+        Some(Path::new("/proc").join(pid.to_string()).exists())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        None
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::temp_directory;
 
     #[test]
     fn marker_path_round_trip() {
@@ -80,4 +440,90 @@ mod tests {
     fn rejects_legacy_port_only_marker() {
         assert!(Marker::try_from_path(Path::new("54321.server")).is_none());
     }
+
+    #[test]
+    fn metadata_round_trip() {
+        let metadata = MarkerMetadata {
+            design_name: Some("counter\ntest".into()),
+            start_time: Some(1_700_000_000.5),
+            ..MarkerMetadata::for_current_process()
+        };
+        let parsed = MarkerMetadata::parse(&metadata.to_string());
+        assert_eq!(parsed.design_name.as_deref(), Some("counter test"));
+        assert_eq!(parsed.start_time, metadata.start_time);
+        assert_eq!(parsed.pid, metadata.pid);
+        assert_eq!(parsed.protocol_version, Some(ProtocolVersion::CURRENT));
+
+        assert_eq!(MarkerMetadata::parse(""), MarkerMetadata::default());
+        assert_eq!(
+            MarkerMetadata::parse("pid=abc\nfuture_key=1\nuser=alice\n"),
+            MarkerMetadata {
+                user: Some("alice".into()),
+                ..MarkerMetadata::default()
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_directory_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = temp_directory().join("private");
+        create_private_directory(&directory).unwrap();
+        let mode = fs::metadata(&directory).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        fs::remove_dir_all(directory.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn guard_removes_marker() {
+        let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let marker = Marker {
+            port: listener.local_addr().unwrap().port(),
+            simulation_id: SimulationId::new_random(),
+        };
+        let directory = temp_directory();
+        let guard =
+            MarkerGuard::create_in(&directory, marker, &MarkerMetadata::for_current_process())
+                .unwrap();
+        let path = guard.path().to_owned();
+        assert_eq!(path.parent(), Some(directory.as_path()));
+        assert_eq!(
+            MarkerMetadata::read(&path).unwrap().pid,
+            Some(std::process::id())
+        );
+        assert!(!is_stale(&path, marker));
+
+        drop(listener);
+        assert!(is_stale(&path, marker));
+        drop(guard);
+        assert!(!path.exists());
+        fs::remove_dir(directory).unwrap();
+    }
+
+    #[test]
+    fn markers_of_other_hosts_are_not_stale() {
+        let marker = Marker {
+            // nothing listens on port 1, and the process doesn't exist here
+            port: 1,
+            simulation_id: SimulationId::new_random(),
+        };
+        let mut metadata = MarkerMetadata {
+            pid: Some(u32::MAX),
+            hostname: Some("other-host.invalid".into()),
+            ..MarkerMetadata::default()
+        };
+        let directory = temp_directory();
+        let guard = MarkerGuard::create_in(&directory, marker, &metadata).unwrap();
+        if current_hostname().is_some() {
+            assert!(!is_stale(guard.path(), marker));
+        }
+
+        metadata.hostname = current_hostname();
+        guard.update_metadata(&metadata).unwrap();
+        assert!(is_stale(guard.path(), marker));
+        drop(guard);
+        fs::remove_dir(directory).unwrap();
+    }
 }
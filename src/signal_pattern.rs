@@ -0,0 +1,373 @@
+//! Selection of signals by patterns over their hierarchical paths, like `tb.dut.*.clk`.
+//!
+//! Paths are formed as described in [`hierarchy_index`](crate::hierarchy_index).
+//! Glob patterns support these wildcards:
+//! - `?` matches a single character except `.`
+//! - `*` matches any number of characters except `.`, i.e. within one path segment
+//! - `**` matches any number of characters including `.`, i.e. across path segments
+//!
+//! Regular expressions require the `regex` cargo feature and must match the whole path.
+
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+use compact_str::CompactString;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::design_hierarchy::SignalElementId;
+use crate::design_hierarchy::SignalType;
+use crate::hierarchy_index::HierarchyIndex;
+use crate::hierarchy_index::IndexedSignal;
+
+/// A pattern over hierarchical signal paths.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PathPattern {
+    Glob(CompactString),
+    Regex(CompactString),
+}
+
+/// Category of a [`SignalType`], used to filter signals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SignalKind {
+    Bit,
+    Logic,
+    Integer,
+    Real,
+    Enumeration,
+    Array,
+    Record,
+    Unsupported,
+}
+
+impl From<&SignalType> for SignalKind {
+    fn from(typ: &SignalType) -> Self {
+        match typ {
+            SignalType::Bit => SignalKind::Bit,
+            SignalType::Logic => SignalKind::Logic,
+            SignalType::Integer { .. } => SignalKind::Integer,
+            SignalType::Real { .. } => SignalKind::Real,
+            SignalType::Enumeration { .. } => SignalKind::Enumeration,
+            SignalType::Array { .. } => SignalKind::Array,
+            SignalType::Record { .. } => SignalKind::Record,
+            SignalType::Unsupported => SignalKind::Unsupported,
+        }
+    }
+}
+
+/// Selects signals by path, depth and type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignalPattern {
+    pub path: PathPattern,
+    /// Only match signals whose path has at most this many segments, e.g. 2 for `tb.clk`.
+    pub max_depth: Option<u32>,
+    /// Only match signals of these kinds; an empty list matches all kinds.
+    pub kinds: Vec<SignalKind>,
+}
+
+impl SignalPattern {
+    pub const fn new(path: PathPattern) -> Self {
+        Self {
+            path,
+            max_depth: None,
+            kinds: vec![],
+        }
+    }
+
+    /// Shorthand for a glob pattern without depth or type restrictions.
+    pub fn glob(pattern: impl Into<CompactString>) -> Self {
+        Self::new(PathPattern::Glob(pattern.into()))
+    }
+
+    /// Shorthand for a regex pattern without depth or type restrictions.
+    pub fn regex(pattern: impl Into<CompactString>) -> Self {
+        Self::new(PathPattern::Regex(pattern.into()))
+    }
+
+    /// Compiles the pattern for repeated matching.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the regex is invalid, or if the `regex` feature is disabled.
+    pub fn compile(&self) -> Result<SignalMatcher<'_>, PatternError> {
+        let path = match &self.path {
+            PathPattern::Glob(glob) => CompiledPath::Glob(Glob::new(glob)),
+            #[cfg(feature = "regex")]
+            PathPattern::Regex(pattern) => CompiledPath::Regex(
+                regex::Regex::new(&format!("^(?:{pattern})$"))
+                    .map_err(|error| PatternError::InvalidRegex(error.to_string()))?,
+            ),
+            #[cfg(not(feature = "regex"))]
+            PathPattern::Regex(_) => return Err(PatternError::RegexUnsupported),
+        };
+        Ok(SignalMatcher {
+            pattern: self,
+            path,
+        })
+    }
+
+    /// Returns the IDs of all scalar elements of all matching signals, in depth-first order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern can't be [compiled](Self::compile).
+    pub fn resolve(
+        &self,
+        index: &HierarchyIndex<'_>,
+    ) -> Result<Vec<SignalElementId>, PatternError> {
+        let matcher = self.compile()?;
+        let mut element_ids = vec![];
+        // shared by all signals, since regexes only match strings
+        #[cfg(feature = "regex")]
+        let mut path = String::new();
+        for signal in index.signals() {
+            let is_match = match &matcher.path {
+                #[cfg(feature = "regex")]
+                CompiledPath::Regex(regex) if matcher.accepts(&signal) => {
+                    path.clear();
+                    path.extend(signal.path_chars());
+                    regex.is_match(&path)
+                },
+                _ => matcher.is_match(&signal),
+            };
+            if is_match {
+                let signal_id = signal.signal.id;
+                element_ids.extend(
+                    (0..signal.signal.typ.element_count())
+                        .map(|element_index| SignalElementId::new(signal_id, element_index)),
+                );
+            }
+        }
+        Ok(element_ids)
+    }
+}
+
+/// A compiled [`SignalPattern`].
+#[derive(Debug, Clone)]
+pub struct SignalMatcher<'pattern> {
+    pattern: &'pattern SignalPattern,
+    path: CompiledPath,
+}
+
+#[derive(Debug, Clone)]
+enum CompiledPath {
+    Glob(Glob),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl SignalMatcher<'_> {
+    pub fn is_match(&self, signal: &IndexedSignal<'_, '_>) -> bool {
+        if !self.accepts(signal) {
+            return false;
+        }
+        match &self.path {
+            CompiledPath::Glob(glob) => glob.is_match(signal.path_chars()),
+            #[cfg(feature = "regex")]
+            CompiledPath::Regex(regex) => regex.is_match(&signal.path()),
+        }
+    }
+
+    /// Returns `true` if the signal's depth and kind match, regardless of its path.
+    fn accepts(&self, signal: &IndexedSignal<'_, '_>) -> bool {
+        if self
+            .pattern
+            .max_depth
+            .is_some_and(|max_depth| signal.depth > max_depth)
+        {
+            return false;
+        }
+        if !self.pattern.kinds.is_empty()
+            && !self
+                .pattern
+                .kinds
+                .contains(&SignalKind::from(&signal.signal.typ))
+        {
+            return false;
+        }
+        true
+    }
+
+    pub fn is_path_match(&self, path: &str) -> bool {
+        match &self.path {
+            CompiledPath::Glob(glob) => glob.is_match(path.chars()),
+            #[cfg(feature = "regex")]
+            CompiledPath::Regex(regex) => regex.is_match(path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GlobToken {
+    Char(char),
+    AnyChar,
+    AnyInSegment,
+    Any,
+}
+
+#[derive(Debug, Clone)]
+struct Glob(Vec<GlobToken>);
+
+impl Glob {
+    fn new(pattern: &str) -> Self {
+        let mut tokens = vec![];
+        let mut chars = pattern.chars().peekable();
+        while let Some(ch) = chars.next() {
+            tokens.push(match ch {
+                '?' => GlobToken::AnyChar,
+                '*' if chars.next_if_eq(&'*').is_some() => GlobToken::Any,
+                '*' => GlobToken::AnyInSegment,
+                _ => GlobToken::Char(ch),
+            });
+        }
+        Self(tokens)
+    }
+
+    fn is_match(&self, path: impl IntoIterator<Item = char>) -> bool {
+        // matches[i] is true if the first i tokens match the path prefix processed so far
+        let mut matches = vec![false; self.0.len() + 1];
+        let mut next = matches.clone();
+        matches[0] = true;
+        Self::skip_wildcards(&self.0, &mut matches);
+        for ch in path {
+            next.fill(false);
+            for (i, token) in self.0.iter().enumerate() {
+                if !matches[i] {
+                    continue;
+                }
+                match *token {
+                    GlobToken::Char(expected) => next[i + 1] |= expected == ch,
+                    GlobToken::AnyChar => next[i + 1] |= ch != '.',
+                    GlobToken::AnyInSegment => next[i] |= ch != '.',
+                    GlobToken::Any => next[i] = true,
+                }
+            }
+            Self::skip_wildcards(&self.0, &mut next);
+            mem::swap(&mut matches, &mut next);
+        }
+        matches[self.0.len()]
+    }
+
+    /// Lets `*` and `**` match the empty string.
+    fn skip_wildcards(tokens: &[GlobToken], matches: &mut [bool]) {
+        for (i, token) in tokens.iter().enumerate() {
+            if matches[i] && matches!(token, GlobToken::AnyInSegment | GlobToken::Any) {
+                matches[i + 1] = true;
+            }
+        }
+    }
+}
+
+/// Error when compiling a [`SignalPattern`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    InvalidRegex(String),
+    /// The crate was built without the `regex` feature.
+    RegexUnsupported,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::InvalidRegex(error) => write!(formatter, "invalid regex: {error}"),
+            PatternError::RegexUnsupported => {
+                formatter.write_str("regex patterns require the `regex` feature")
+            },
+        }
+    }
+}
+
+impl Error for PatternError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::hierarchy;
+    use crate::test_utils::logic_vector;
+    use crate::test_utils::module;
+    use crate::test_utils::signal;
+
+    fn glob_matches(pattern: &str, path: &str) -> bool {
+        Glob::new(pattern).is_match(path.chars())
+    }
+
+    #[test]
+    fn glob_wildcards_respect_segments() {
+        assert!(glob_matches("tb.dut.*.clk", "tb.dut.u_fifo.clk"));
+        assert!(!glob_matches("tb.dut.*.clk", "tb.dut.u_fifo.u_ram.clk"));
+        assert!(glob_matches("tb.**.clk", "tb.dut.u_fifo.u_ram.clk"));
+        assert!(glob_matches("tb.**clk", "tb.clk"));
+        assert!(glob_matches("tb.?ut.*", "tb.dut.valid"));
+        assert!(!glob_matches("tb.?ut", "tb.ut"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("tb.*", "tb.dut.valid"));
+    }
+
+    #[test]
+    fn resolves_elements_of_matching_signals() {
+        let fifo = module(
+            "u_fifo",
+            vec![],
+            vec![
+                signal("clk", 3, SignalType::Bit),
+                signal("count", 4, SignalType::Unsupported),
+            ],
+        );
+        let dut = module(
+            "dut",
+            vec![fifo],
+            vec![
+                signal("clk", 2, SignalType::Logic),
+                signal("data", 5, logic_vector(3, 0)),
+            ],
+        );
+        let hierarchy = hierarchy(vec![module(
+            "tb",
+            vec![dut],
+            vec![signal("clk", 1, SignalType::Bit)],
+        )]);
+        let index = HierarchyIndex::new(&hierarchy);
+        let resolve = |pattern: SignalPattern| {
+            let element_ids = pattern.resolve(&index).unwrap();
+            element_ids
+                .iter()
+                .map(|element_id| (element_id.signal_id.0.get(), element_id.element_index))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(resolve(SignalPattern::glob("tb.**.clk")), [(2, 0), (3, 0)]);
+        assert_eq!(
+            resolve(SignalPattern::glob("tb.dut.data")),
+            [(5, 0), (5, 1), (5, 2), (5, 3)]
+        );
+        assert_eq!(
+            resolve(SignalPattern {
+                max_depth: Some(3),
+                ..SignalPattern::glob("**clk")
+            }),
+            [(1, 0), (2, 0)]
+        );
+        assert_eq!(
+            resolve(SignalPattern {
+                kinds: vec![SignalKind::Bit, SignalKind::Unsupported],
+                ..SignalPattern::glob("tb.dut.**")
+            }),
+            [(3, 0), (4, 0)]
+        );
+        assert!(resolve(SignalPattern::glob("tb.dut")).is_empty());
+        assert_eq!(
+            SignalPattern::regex("tb").resolve(&index).is_ok(),
+            cfg!(feature = "regex")
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_matches_whole_path() {
+        let pattern = SignalPattern::regex("tb\\.dut\\.(rd|wr)_ptr");
+        let matcher = pattern.compile().unwrap();
+        assert!(matcher.is_path_match("tb.dut.wr_ptr"));
+        assert!(!matcher.is_path_match("tb.dut.wr_ptr_next"));
+    }
+}
@@ -0,0 +1,73 @@
+//! Builders for design hierarchies and temporary directories used by the unit tests.
+
+use std::env;
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+
+use crate::SimulationId;
+use crate::design_hierarchy::DesignHierarchy;
+use crate::design_hierarchy::Direction;
+use crate::design_hierarchy::Module;
+use crate::design_hierarchy::ModuleKind;
+use crate::design_hierarchy::Signal;
+use crate::design_hierarchy::SignalInstanceId;
+use crate::design_hierarchy::SignalType;
+use crate::time::PhysicalTime;
+
+/// Returns a hierarchy with a time resolution of 1 fs.
+pub fn hierarchy(root_modules: Vec<Module>) -> DesignHierarchy {
+    DesignHierarchy {
+        simulation_id: SimulationId::ZERO,
+        name: None,
+        start_time: 0.0,
+        time_resolution: PhysicalTime(1),
+        root_modules,
+    }
+}
+
+/// Returns a design entity with architecture `rtl`.
+pub fn module(name: &str, submodules: Vec<Module>, signals: Vec<Signal>) -> Module {
+    Module {
+        name: Some(name.into()),
+        kind: ModuleKind::DesignEntity {
+            entity: name.into(),
+            architecture: "rtl".into(),
+        },
+        submodules,
+        signals,
+    }
+}
+
+/// Returns a signal; `id` must not be 0.
+pub fn signal(name: &str, id: u32, typ: SignalType) -> Signal {
+    Signal {
+        name: name.into(),
+        id: signal_id(id),
+        typ,
+    }
+}
+
+pub fn signal_id(id: u32) -> SignalInstanceId {
+    SignalInstanceId(NonZeroU32::new(id).expect("signal IDs are not 0"))
+}
+
+/// Returns a `std_logic_vector(left downto right)`.
+pub fn logic_vector(left: i32, right: i32) -> SignalType {
+    let element_count = Direction::Downto.length_for(left, right);
+    SignalType::Array {
+        left,
+        right,
+        direction: Direction::Downto,
+        element_count,
+        element_type: Box::new(SignalType::Logic),
+    }
+}
+
+/// Creates an empty directory under the OS temp directory, e.g. for marker files
+/// which must not show up in the real markers directory; the caller removes it.
+pub fn temp_directory() -> PathBuf {
+    let directory = env::temp_dir().join(format!("hdl-sim-test-{}", SimulationId::new_random()));
+    fs::create_dir(&directory).expect("can create a temp directory");
+    directory
+}
@@ -1,15 +1,23 @@
 use std::fmt;
 use std::ops;
 use std::ops::Range;
+use std::str::FromStr;
 
 use serde::Deserialize;
 use serde::Serialize;
 
 use crate::serde_utils;
 
+/// A point in simulation time or a non-negative duration, in femtoseconds.
+///
+/// Unlike VHDL's signed `time` type, simulation time is never negative;
+/// signed differences are represented by [`TimeDelta`].
+///
+/// The `+`, `-` and `*` operators saturate; `/` and `%` panic on division by zero.
+/// The `checked_*` methods return `None` instead.
+///
 /// This type is serialized as a string-encoded integer for human-readable formats,
 /// and as a raw integer for binary formats.
-// TODO should this be an i64 to reflect VHDL's time type?
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
 pub struct PhysicalTime(pub u64);
 
@@ -22,6 +30,129 @@ impl PhysicalTime {
     pub fn empty_time_span() -> Range<PhysicalTime> {
         Self::ZERO..Self::ZERO
     }
+
+    pub const fn checked_add(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_add(rhs.0) {
+            Some(time) => Some(Self(time)),
+            None => None,
+        }
+    }
+
+    pub const fn checked_sub(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_sub(rhs.0) {
+            Some(time) => Some(Self(time)),
+            None => None,
+        }
+    }
+
+    pub const fn checked_mul(self, rhs: u64) -> Option<Self> {
+        match self.0.checked_mul(rhs) {
+            Some(time) => Some(Self(time)),
+            None => None,
+        }
+    }
+
+    pub const fn checked_div(self, rhs: u64) -> Option<Self> {
+        match self.0.checked_div(rhs) {
+            Some(time) => Some(Self(time)),
+            None => None,
+        }
+    }
+
+    /// Returns how many times `rhs` fits into the time, or `None` if `rhs` is zero.
+    pub const fn checked_div_time(self, rhs: Self) -> Option<u64> {
+        self.0.checked_div(rhs.0)
+    }
+
+    pub const fn checked_rem(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_rem(rhs.0) {
+            Some(time) => Some(Self(time)),
+            None => None,
+        }
+    }
+
+    /// Adds a signed number of femtoseconds, returning `None` if the result would be negative or overflow.
+    pub const fn checked_add_signed(self, rhs: i64) -> Option<Self> {
+        match self.0.checked_add_signed(rhs) {
+            Some(time) => Some(Self(time)),
+            None => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    #[must_use]
+    pub const fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+
+    #[must_use]
+    pub const fn saturating_mul(self, rhs: u64) -> Self {
+        Self(self.0.saturating_mul(rhs))
+    }
+
+    /// Adds a signed number of femtoseconds, saturating at zero and [`Self::MAX`].
+    #[must_use]
+    pub const fn saturating_add_signed(self, rhs: i64) -> Self {
+        Self(self.0.saturating_add_signed(rhs))
+    }
+
+    /// Returns `true` if the time is a multiple of `resolution`; every time is aligned to a zero resolution.
+    pub const fn is_aligned_to(self, resolution: Self) -> bool {
+        resolution.0 == 0 || self.0.is_multiple_of(resolution.0)
+    }
+
+    /// Rounds down to a multiple of `resolution`; a zero resolution leaves the time unchanged.
+    #[must_use]
+    pub const fn floor_to(self, resolution: Self) -> Self {
+        if resolution.0 == 0 {
+            return self;
+        }
+        Self(self.0 - self.0 % resolution.0)
+    }
+
+    /// Rounds up to a multiple of `resolution`, saturating at the largest representable multiple.
+    #[must_use]
+    pub const fn ceil_to(self, resolution: Self) -> Self {
+        let floor = self.floor_to(resolution);
+        if floor.0 == self.0 {
+            return self;
+        }
+        match floor.0.checked_add(resolution.0) {
+            Some(ceil) => Self(ceil),
+            None => floor,
+        }
+    }
+
+    /// Rounds to the nearest multiple of `resolution`, rounding ties up.
+    #[must_use]
+    pub const fn round_to(self, resolution: Self) -> Self {
+        let floor = self.floor_to(resolution);
+        let remainder = self.0 - floor.0;
+        if remainder != 0 && remainder >= resolution.0 - remainder {
+            self.ceil_to(resolution)
+        } else {
+            floor
+        }
+    }
+
+    /// Returns the number of fraction digits needed to display multiples of this resolution in `unit` exactly,
+    /// e.g. 3 for a resolution of 1 ps in `ns`, and 0 in `ps` or larger resolutions.
+    ///
+    /// Limited to [`TimeDisplay::MAX_FRACTION_DIGITS`] for non-terminating fractions of minutes and hours.
+    pub fn fraction_digits(self, unit: TimeUnit) -> usize {
+        let divisor = u128::from(unit.femtoseconds());
+        let mut remainder = u128::from(self.0) % divisor;
+        let mut digits = 0;
+        while remainder != 0 && digits < TimeDisplay::MAX_FRACTION_DIGITS {
+            remainder = remainder * 10 % divisor;
+            digits += 1;
+        }
+        digits
+    }
 }
 
 impl From<u64> for PhysicalTime {
@@ -34,7 +165,7 @@ impl ops::Sub<Self> for PhysicalTime {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        Self(self.0.saturating_sub(rhs.0))
+        self.saturating_sub(rhs)
     }
 }
 
@@ -50,7 +181,7 @@ impl ops::Mul<u64> for PhysicalTime {
     type Output = Self;
 
     fn mul(self, rhs: u64) -> Self::Output {
-        Self(self.0 * rhs)
+        self.saturating_mul(rhs)
     }
 }
 
@@ -58,7 +189,7 @@ impl ops::Mul<PhysicalTime> for u64 {
     type Output = PhysicalTime;
 
     fn mul(self, rhs: PhysicalTime) -> Self::Output {
-        PhysicalTime(self * rhs.0)
+        rhs.saturating_mul(self)
     }
 }
 
@@ -74,7 +205,7 @@ impl ops::Add for PhysicalTime {
     type Output = Self;
 
     fn add(self, rhs: PhysicalTime) -> Self::Output {
-        Self(self.0.saturating_add(rhs.0))
+        self.saturating_add(rhs)
     }
 }
 
@@ -84,6 +215,13 @@ impl ops::AddAssign for PhysicalTime {
     }
 }
 
+impl ops::SubAssign for PhysicalTime {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+/// Panics if `rhs` is zero, see [`PhysicalTime::checked_div_time`].
 impl ops::Div for PhysicalTime {
     type Output = u64;
 
@@ -98,6 +236,240 @@ impl fmt::Debug for PhysicalTime {
     }
 }
 
+impl PhysicalTime {
+    /// Returns the largest unit in which the time is displayed without rounding.
+    ///
+    /// Minutes and hours are only used for whole multiples, as their fractions may not be finite decimals.
+    #[must_use]
+    pub fn best_unit(self) -> TimeUnit {
+        TimeUnit::ALL
+            .into_iter()
+            .rev()
+            .find(|&unit| match unit {
+                TimeUnit::Min | TimeUnit::Hr => {
+                    self.0 != 0 && self.0.is_multiple_of(unit.femtoseconds())
+                },
+                _ => self.0 >= unit.femtoseconds(),
+            })
+            .unwrap_or(TimeUnit::Fs)
+    }
+
+    /// Returns a value which displays the time in the given unit, e.g. `1.5 us`.
+    ///
+    /// Without a precision (as in `{:.3}`), all significant decimals are shown,
+    /// except for non-terminating fractions of minutes and hours,
+    /// which are cut after [`TimeDisplay::MAX_FRACTION_DIGITS`].
+    /// With a precision, the value is rounded half up.
+    #[must_use]
+    pub const fn display_in(self, unit: TimeUnit) -> TimeDisplay {
+        TimeDisplay { time: self, unit }
+    }
+}
+
+/// Displays the time in its [best unit](Self::best_unit), e.g. `10 ns` or `1.5 us`,
+/// which parses back to the same time.
+impl fmt::Display for PhysicalTime {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.display_in(self.best_unit()), formatter)
+    }
+}
+
+/// Parses a VHDL time literal such as `10 ns`, `1.5 us`, `1_000 ps` or `2.5e3 fs`.
+///
+/// Units are case-insensitive; the value must be a whole number of femtoseconds.
+impl FromStr for PhysicalTime {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let number_length = value
+            .trim_end_matches(|char: char| char.is_ascii_alphabetic())
+            .len();
+        let (number, unit) = value.split_at(number_length);
+        let unit: TimeUnit = unit.parse()?;
+        let number = number.trim_end();
+
+        let (mantissa, exponent) = match number.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => {
+                let exponent: i32 = exponent
+                    .trim_start_matches('+')
+                    .parse()
+                    .map_err(|_| format!("invalid exponent in time literal {value:?}"))?;
+                (mantissa, exponent)
+            },
+            None => (number, 0),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(format!("missing number in time literal {value:?}"));
+        }
+
+        let out_of_range = || format!("time literal {value:?} is out of range");
+        let mut digits: u128 = 0;
+        let mut fraction_length = 0_i64;
+        for (char, is_fraction) in integer
+            .chars()
+            .map(|char| (char, false))
+            .chain(fraction.chars().map(|char| (char, true)))
+        {
+            if char == '_' {
+                continue;
+            }
+            let digit = char
+                .to_digit(10)
+                .ok_or_else(|| format!("invalid character {char:?} in time literal {value:?}"))?;
+            digits = digits
+                .checked_mul(10)
+                .and_then(|digits| digits.checked_add(u128::from(digit)))
+                .ok_or_else(out_of_range)?;
+            fraction_length += i64::from(is_fraction);
+        }
+
+        let mut femtoseconds = digits
+            .checked_mul(u128::from(unit.femtoseconds()))
+            .ok_or_else(out_of_range)?;
+        let scale = i64::from(exponent) - fraction_length;
+        if femtoseconds != 0 {
+            let power = u32::try_from(scale.unsigned_abs())
+                .ok()
+                .and_then(|power| 10_u128.checked_pow(power));
+            if scale >= 0 {
+                femtoseconds = power
+                    .and_then(|power| femtoseconds.checked_mul(power))
+                    .ok_or_else(out_of_range)?;
+            } else {
+                match power {
+                    Some(power) if femtoseconds.is_multiple_of(power) => femtoseconds /= power,
+                    _ => {
+                        return Err(format!(
+                            "time literal {value:?} is not a whole number of femtoseconds"
+                        ));
+                    },
+                }
+            }
+        }
+        u64::try_from(femtoseconds)
+            .map(Self)
+            .map_err(|_| out_of_range())
+    }
+}
+
+/// Units of VHDL's predefined `time` type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum TimeUnit {
+    Fs,
+    Ps,
+    Ns,
+    Us,
+    Ms,
+    Sec,
+    Min,
+    Hr,
+}
+
+impl TimeUnit {
+    /// All units in ascending order.
+    pub const ALL: [Self; 8] = [
+        Self::Fs,
+        Self::Ps,
+        Self::Ns,
+        Self::Us,
+        Self::Ms,
+        Self::Sec,
+        Self::Min,
+        Self::Hr,
+    ];
+
+    pub const fn femtoseconds(self) -> u64 {
+        match self {
+            TimeUnit::Fs => 1,
+            TimeUnit::Ps => 1_000,
+            TimeUnit::Ns => 1_000_000,
+            TimeUnit::Us => 1_000_000_000,
+            TimeUnit::Ms => 1_000_000_000_000,
+            TimeUnit::Sec => 1_000_000_000_000_000,
+            TimeUnit::Min => 60_000_000_000_000_000,
+            TimeUnit::Hr => 3_600_000_000_000_000_000,
+        }
+    }
+
+    /// Returns the unit's VHDL name.
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            TimeUnit::Fs => "fs",
+            TimeUnit::Ps => "ps",
+            TimeUnit::Ns => "ns",
+            TimeUnit::Us => "us",
+            TimeUnit::Ms => "ms",
+            TimeUnit::Sec => "sec",
+            TimeUnit::Min => "min",
+            TimeUnit::Hr => "hr",
+        }
+    }
+}
+
+impl fmt::Display for TimeUnit {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.symbol())
+    }
+}
+
+impl FromStr for TimeUnit {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|unit| unit.symbol().eq_ignore_ascii_case(value))
+            .ok_or_else(|| format!("unknown time unit {value:?}"))
+    }
+}
+
+/// Displays a [`PhysicalTime`] in a fixed unit, see [`PhysicalTime::display_in`].
+#[derive(Debug, Clone, Copy)]
+pub struct TimeDisplay {
+    time: PhysicalTime,
+    unit: TimeUnit,
+}
+
+impl TimeDisplay {
+    /// Maximum number of fraction digits shown without an explicit precision.
+    pub const MAX_FRACTION_DIGITS: usize = 20;
+}
+
+impl fmt::Display for TimeDisplay {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let divisor = u128::from(self.unit.femtoseconds());
+        let mut integer = u128::from(self.time.0) / divisor;
+        let mut remainder = u128::from(self.time.0) % divisor;
+
+        let mut fraction: Vec<u8> = vec![];
+        let digit_count = formatter.precision().unwrap_or(Self::MAX_FRACTION_DIGITS);
+        while fraction.len() < digit_count && (remainder != 0 || formatter.precision().is_some()) {
+            remainder *= 10;
+            fraction.push((remainder / divisor) as u8);
+            remainder %= divisor;
+        }
+        if formatter.precision().is_some() && remainder * 2 >= divisor {
+            // round half up, carrying into the integer part if all digits are 9
+            let carry = fraction.iter_mut().rev().all(|digit| {
+                *digit = (*digit + 1) % 10;
+                *digit == 0
+            });
+            integer += u128::from(carry);
+        }
+
+        write!(formatter, "{integer}")?;
+        if !fraction.is_empty() {
+            formatter.write_str(".")?;
+            for digit in fraction {
+                write!(formatter, "{digit}")?;
+            }
+        }
+        write!(formatter, " {unit}", unit = self.unit)
+    }
+}
+
 impl Serialize for PhysicalTime {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -139,6 +511,57 @@ impl LogicalTime {
         physical: PhysicalTime::MAX,
         delta: Delta::MAX,
     };
+
+    /// Returns the difference `self - earlier` per component, or `None` if a component doesn't fit in an `i64`.
+    pub fn checked_sub(self, earlier: Self) -> Option<TimeDelta> {
+        Some(TimeDelta {
+            physical: signed_difference(self.physical.0, earlier.physical.0)?,
+            delta: signed_difference(self.delta.0, earlier.delta.0)?,
+        })
+    }
+
+    /// Same as [`Self::checked_sub`], but saturating at the bounds of `i64`.
+    #[must_use]
+    pub fn saturating_sub(self, earlier: Self) -> TimeDelta {
+        let saturate = |later: u64, earlier: u64| {
+            signed_difference(later, earlier).unwrap_or(if later > earlier {
+                i64::MAX
+            } else {
+                i64::MIN
+            })
+        };
+        TimeDelta {
+            physical: saturate(self.physical.0, earlier.physical.0),
+            delta: saturate(self.delta.0, earlier.delta.0),
+        }
+    }
+
+    /// Adds a difference per component, or returns `None` if a component would be negative or overflow.
+    pub const fn checked_add(self, difference: TimeDelta) -> Option<Self> {
+        let Some(physical) = self.physical.checked_add_signed(difference.physical) else {
+            return None;
+        };
+        let Some(delta) = self.delta.0.checked_add_signed(difference.delta) else {
+            return None;
+        };
+        Some(Self {
+            physical,
+            delta: Delta(delta),
+        })
+    }
+
+    /// Same as [`Self::checked_add`], but saturating each component.
+    #[must_use]
+    pub const fn saturating_add(self, difference: TimeDelta) -> Self {
+        Self {
+            physical: self.physical.saturating_add_signed(difference.physical),
+            delta: Delta(self.delta.0.saturating_add_signed(difference.delta)),
+        }
+    }
+}
+
+fn signed_difference(later: u64, earlier: u64) -> Option<i64> {
+    i64::try_from(i128::from(later) - i128::from(earlier)).ok()
 }
 
 impl fmt::Display for LogicalTime {
@@ -158,6 +581,25 @@ impl fmt::Debug for LogicalTime {
     }
 }
 
+/// Saturating difference, see [`LogicalTime::saturating_sub`];
+/// use [`LogicalTime::checked_sub`] to detect differences which don't fit in an `i64`.
+impl ops::Sub for LogicalTime {
+    type Output = TimeDelta;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.saturating_sub(rhs)
+    }
+}
+
+/// Saturating addition, see [`LogicalTime::saturating_add`].
+impl ops::Add<TimeDelta> for LogicalTime {
+    type Output = Self;
+
+    fn add(self, rhs: TimeDelta) -> Self::Output {
+        self.saturating_add(rhs)
+    }
+}
+
 impl ops::Add<Delta> for LogicalTime {
     type Output = Self;
 
@@ -224,6 +666,10 @@ impl From<(u64, u64)> for LogicalTime {
     }
 }
 
+/// Index of a delta cycle within a time step.
+///
+/// Like for [`PhysicalTime`], the `+`, `-` and `*` operators saturate and `/` panics on division by zero.
+///
 /// This type is serialized as a string-encoded integer for human-readable formats,
 /// and as a raw integer for binary formats.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
@@ -232,6 +678,49 @@ pub struct Delta(pub u64);
 impl Delta {
     pub const ZERO: Delta = Self(0);
     pub const MAX: Delta = Self(u64::MAX);
+
+    pub const fn checked_add(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_add(rhs.0) {
+            Some(delta) => Some(Self(delta)),
+            None => None,
+        }
+    }
+
+    pub const fn checked_sub(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_sub(rhs.0) {
+            Some(delta) => Some(Self(delta)),
+            None => None,
+        }
+    }
+
+    pub const fn checked_mul(self, rhs: u64) -> Option<Self> {
+        match self.0.checked_mul(rhs) {
+            Some(delta) => Some(Self(delta)),
+            None => None,
+        }
+    }
+
+    pub const fn checked_div(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_div(rhs.0) {
+            Some(delta) => Some(Self(delta)),
+            None => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    #[must_use]
+    pub const fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+
+    #[must_use]
+    pub const fn saturating_mul(self, rhs: u64) -> Self {
+        Self(self.0.saturating_mul(rhs))
+    }
 }
 
 impl From<u64> for Delta {
@@ -244,7 +733,7 @@ impl ops::Add for Delta {
     type Output = Self;
 
     fn add(self, rhs: Delta) -> Self::Output {
-        Delta(self.0 + rhs.0)
+        self.saturating_add(rhs)
     }
 }
 
@@ -266,7 +755,7 @@ impl ops::Mul<u64> for Delta {
     type Output = Self;
 
     fn mul(self, rhs: u64) -> Self::Output {
-        Self(self.0 * rhs)
+        self.saturating_mul(rhs)
     }
 }
 
@@ -274,7 +763,7 @@ impl ops::Sub<Delta> for Delta {
     type Output = Self;
 
     fn sub(self, rhs: Delta) -> Self::Output {
-        Delta(self.0 - rhs.0)
+        self.saturating_sub(rhs)
     }
 }
 
@@ -295,3 +784,246 @@ impl<'de> Deserialize<'de> for Delta {
         serde_utils::deserialize(deserializer, "a string-encoded u64").map(Self)
     }
 }
+
+/// Signed difference between two [`LogicalTime`]s, per component.
+///
+/// Adding the difference `b - a` to `a` yields `b`.
+/// Differences are ordered like logical times: by physical time first, then by delta cycles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+pub struct TimeDelta {
+    /// Difference in femtoseconds.
+    pub physical: i64,
+    /// Difference in delta cycles.
+    pub delta: i64,
+}
+
+impl TimeDelta {
+    pub const ZERO: Self = Self::new(0, 0);
+
+    pub const fn new(physical: i64, delta: i64) -> Self {
+        Self { physical, delta }
+    }
+
+    /// Returns `true` if the difference points backwards in time.
+    pub const fn is_negative(&self) -> bool {
+        self.physical < 0 || (self.physical == 0 && self.delta < 0)
+    }
+
+    /// Returns the physical part as a duration, or `None` if it is negative.
+    pub const fn physical_duration(&self) -> Option<PhysicalTime> {
+        if self.physical < 0 {
+            None
+        } else {
+            Some(PhysicalTime(self.physical.unsigned_abs()))
+        }
+    }
+}
+
+impl ops::Neg for TimeDelta {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::new(self.physical.saturating_neg(), self.delta.saturating_neg())
+    }
+}
+
+impl fmt::Display for TimeDelta {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.physical < 0 { "-" } else { "" };
+        write!(
+            formatter,
+            "({sign}{time}, {delta} δ)",
+            time = PhysicalTime(self.physical.unsigned_abs()),
+            delta = self.delta,
+        )
+    }
+}
+
+/// Set operations on half-open ranges such as `Range<LogicalTime>`.
+///
+/// Empty ranges are contained in every range, and don't overlap any range.
+pub trait RangeExt: Sized {
+    /// Returns `true` if every point of `other` lies within `self`.
+    fn contains_range(&self, other: &Self) -> bool;
+
+    /// Returns `true` if the ranges have at least one point in common.
+    fn overlaps(&self, other: &Self) -> bool;
+
+    /// Returns the points in both ranges, or `None` if they don't overlap.
+    fn intersection(&self, other: &Self) -> Option<Self>;
+
+    /// Returns the smallest range containing both ranges, or `None` if there is a gap between them.
+    fn union(&self, other: &Self) -> Option<Self>;
+}
+
+impl<T: Copy + Ord> RangeExt for Range<T> {
+    fn contains_range(&self, other: &Self) -> bool {
+        other.is_empty() || (self.start <= other.start && other.end <= self.end)
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        self.start.max(other.start) < self.end.min(other.end)
+    }
+
+    fn intersection(&self, other: &Self) -> Option<Self> {
+        let intersection = self.start.max(other.start)..self.end.min(other.end);
+        (!intersection.is_empty()).then_some(intersection)
+    }
+
+    fn union(&self, other: &Self) -> Option<Self> {
+        if other.is_empty() {
+            return Some(self.clone());
+        }
+        if self.is_empty() {
+            return Some(other.clone());
+        }
+        if self.start.max(other.start) > self.end.min(other.end) {
+            return None;
+        }
+        Some(self.start.min(other.start)..self.end.max(other.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::to_simulator::RunUntil;
+
+    #[test]
+    fn parses_time_literals() {
+        assert_eq!("10 ns".parse(), Ok(PhysicalTime(10_000_000)));
+        assert_eq!("1.5 us".parse(), Ok(PhysicalTime(1_500_000_000)));
+        assert_eq!("1_000ps".parse(), Ok(PhysicalTime(1_000_000)));
+        assert_eq!("2.5e3 FS".parse(), Ok(PhysicalTime(2_500)));
+        assert_eq!(
+            "0.5 hr".parse(),
+            Ok(PhysicalTime(1_800_000_000_000_000_000))
+        );
+        assert_eq!("0 fs".parse(), Ok(PhysicalTime::ZERO));
+        for invalid in ["10", "0.5 fs", "-1 ns", "1 ks", "1.2.3 ns", "20 hr", ". ns"] {
+            assert!(invalid.parse::<PhysicalTime>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn displays_time_in_best_unit() {
+        let cases = [
+            (PhysicalTime::ZERO, "0 fs"),
+            (PhysicalTime(10_000_000), "10 ns"),
+            (PhysicalTime(1_500_000_000), "1.5 us"),
+            (PhysicalTime(1_000_001), "1.000001 ns"),
+            (PhysicalTime(90_000_000_000_000_000), "90 sec"),
+            (PhysicalTime(7_200_000_000_000_000_000), "2 hr"),
+            (PhysicalTime::MAX, "18446.744073709551615 sec"),
+        ];
+        for (time, text) in cases {
+            assert_eq!(time.to_string(), text);
+            assert_eq!(text.parse(), Ok(time));
+        }
+
+        let time = PhysicalTime(1_995_000);
+        assert_eq!(format!("{:.2}", time.display_in(TimeUnit::Ns)), "2.00 ns");
+        assert_eq!(format!("{:.3}", time.display_in(TimeUnit::Ns)), "1.995 ns");
+        assert_eq!(format!("{:.1}", time), "2.0 ns");
+        assert_eq!(time.display_in(TimeUnit::Ps).to_string(), "1995 ps");
+        assert_eq!(
+            PhysicalTime(20_000_000_000_000_000)
+                .display_in(TimeUnit::Min)
+                .to_string(),
+            format!("0.{} min", "3".repeat(TimeDisplay::MAX_FRACTION_DIGITS)),
+        );
+    }
+
+    #[test]
+    fn snaps_to_resolution() {
+        let resolution = PhysicalTime(1_000);
+        assert!(PhysicalTime(3_000).is_aligned_to(resolution));
+        assert!(!PhysicalTime(3_001).is_aligned_to(resolution));
+        assert!(PhysicalTime(3_001).is_aligned_to(PhysicalTime::ZERO));
+        assert!(
+            RunUntil::UntilTime {
+                deadline: PhysicalTime(3_001)
+            }
+            .validate(PhysicalTime::ZERO)
+            .is_ok()
+        );
+        assert_eq!(
+            PhysicalTime(3_499).floor_to(resolution),
+            PhysicalTime(3_000)
+        );
+        assert_eq!(PhysicalTime(3_001).ceil_to(resolution), PhysicalTime(4_000));
+        assert_eq!(
+            PhysicalTime(3_499).round_to(resolution),
+            PhysicalTime(3_000)
+        );
+        assert_eq!(
+            PhysicalTime(3_500).round_to(resolution),
+            PhysicalTime(4_000)
+        );
+        assert_eq!(
+            PhysicalTime::MAX.ceil_to(resolution),
+            PhysicalTime::MAX.floor_to(resolution)
+        );
+        assert_eq!(
+            PhysicalTime(3_001).round_to(PhysicalTime::ZERO),
+            PhysicalTime(3_001)
+        );
+
+        assert_eq!(resolution.fraction_digits(TimeUnit::Ns), 3);
+        assert_eq!(resolution.fraction_digits(TimeUnit::Ps), 0);
+        assert_eq!(PhysicalTime(10_000).fraction_digits(TimeUnit::Ns), 2);
+    }
+
+    #[test]
+    fn checked_and_saturating_arithmetic() {
+        assert_eq!(PhysicalTime::MAX * 2, PhysicalTime::MAX);
+        assert_eq!(PhysicalTime::MAX.checked_mul(2), None);
+        assert_eq!(PhysicalTime(7).checked_div(0), None);
+        assert_eq!(PhysicalTime(7).checked_div_time(PhysicalTime(2)), Some(3));
+        assert_eq!(PhysicalTime(7).checked_div_time(PhysicalTime::ZERO), None);
+        assert_eq!(
+            PhysicalTime(7).checked_rem(PhysicalTime(4)),
+            Some(PhysicalTime(3))
+        );
+        assert_eq!(PhysicalTime(3).checked_sub(PhysicalTime(4)), None);
+        assert_eq!(Delta(1) - Delta(2), Delta::ZERO);
+        assert_eq!(Delta::MAX + Delta(1), Delta::MAX);
+        assert_eq!(Delta::MAX.checked_add(Delta(1)), None);
+    }
+
+    #[test]
+    fn logical_time_differences() {
+        let earlier = LogicalTime::from((10, 3));
+        let later = LogicalTime::from((25, 1));
+        let difference = later - earlier;
+        assert_eq!(difference, TimeDelta::new(15, -2));
+        assert!(!difference.is_negative());
+        assert!((-difference).is_negative());
+        assert_eq!(earlier + difference, later);
+        assert_eq!(later.checked_add(-difference), Some(earlier));
+        assert_eq!(earlier.checked_add(-difference), None);
+        assert_eq!(difference.to_string(), "(15 fs, -2 δ)");
+        assert_eq!(LogicalTime::MAX.checked_sub(LogicalTime::ZERO), None);
+        assert_eq!(
+            LogicalTime::ZERO - LogicalTime::MAX,
+            TimeDelta::new(i64::MIN, i64::MIN),
+        );
+    }
+
+    #[test]
+    fn range_operations() {
+        let time = |physical: u64| LogicalTime::from(physical);
+        let range = time(10)..time(20);
+        assert!(range.contains_range(&(time(12)..time(20))));
+        assert!(!range.contains_range(&(time(12)..time(21))));
+        assert!(range.contains_range(&(time(30)..time(30))));
+        assert_eq!(
+            range.intersection(&(time(15)..time(30))),
+            Some(time(15)..time(20))
+        );
+        assert_eq!(range.intersection(&(time(20)..time(30))), None);
+        assert!(!range.overlaps(&(time(20)..time(30))));
+        assert_eq!(range.union(&(time(20)..time(30))), Some(time(10)..time(30)));
+        assert_eq!(range.union(&(time(21)..time(30))), None);
+    }
+}
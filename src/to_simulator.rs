@@ -1,12 +1,68 @@
+use std::fmt;
+
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::breakpoint::Breakpoint;
+use crate::breakpoint::BreakpointId;
+use crate::breakpoint::ReportBreakpoint;
 use crate::design_hierarchy::SignalElementId;
+use crate::from_simulator::CommandError;
+use crate::from_simulator::RawValue;
+use crate::handshake::Hello;
+use crate::hierarchy_index::HierarchyIndex;
+use crate::signal_pattern::SignalPattern;
 use crate::time::PhysicalTime;
+use crate::value::Value;
+
+/// Client-chosen sequence number which identifies a [`Request`].
+///
+/// The simulator answers every request with exactly one reply carrying the same ID:
+/// the command's dedicated reply, e.g. [`PatternSubscribed`](crate::from_simulator::SimulationUpdate::PatternSubscribed)
+/// for [`Command::SubscribePattern`], if it has one and succeeded,
+/// and a [`CommandResult`](crate::from_simulator::SimulationUpdate::CommandResult) otherwise.
+///
+/// Only the handshake differs: [`Command::Hello`] is answered by a [`Welcome`](crate::from_simulator::SimulationUpdate::Welcome),
+/// which has no request ID, and a rejected `Hello` or any request before it by an
+/// [`Error`](crate::from_simulator::SimulationUpdate::Error) attributed to the request.
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Default,
+)]
+pub struct RequestId(pub u32);
+
+impl RequestId {
+    /// Returns the next sequence number, wrapping around on overflow.
+    #[must_use]
+    pub const fn next(self) -> Self {
+        Self(self.0.wrapping_add(1))
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "#{id}", id = self.0)
+    }
+}
+
+/// A message sent to the simulator: a command tagged with a request ID.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Request {
+    pub id: RequestId,
+    pub command: Command,
+}
+
+impl Request {
+    pub const fn new(id: RequestId, command: Command) -> Self {
+        Self { id, command }
+    }
+}
 
 /// A command to control the simulation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Command {
+    /// Opens the session; must be the first command sent on a connection.
+    Hello(Hello),
+
     /// Starts or resumes the simulation.
     RunSimulation { until: RunUntil },
 
@@ -21,6 +77,145 @@ pub enum Command {
 
     /// Unsubscribes from the given signals.
     Unsubscribe(Vec<SignalElementId>),
+
+    /// Subscribes to all elements of all signals matching the pattern.
+    ///
+    /// The simulator replies with [`SimulationUpdate::PatternSubscribed`](crate::from_simulator::SimulationUpdate::PatternSubscribed).
+    SubscribePattern(SignalPattern),
+
+    /// Executes exactly one delta cycle of the paused simulation.
+    ///
+    /// The simulator replies with [`SimulationUpdate::StepCompleted`](crate::from_simulator::SimulationUpdate::StepCompleted)
+    /// and stays paused.
+    StepDeltaCycle,
+
+    /// Executes the remaining delta cycles of the current time step of the paused simulation,
+    /// stopping before physical time advances.
+    ///
+    /// The simulator replies with [`SimulationUpdate::StepCompleted`](crate::from_simulator::SimulationUpdate::StepCompleted)
+    /// and stays paused.
+    FinishTimeStep,
+
+    /// Advances the paused simulation to the next scheduled event and executes its first delta cycle.
+    ///
+    /// The simulator replies with [`SimulationUpdate::StepCompleted`](crate::from_simulator::SimulationUpdate::StepCompleted)
+    /// and stays paused.
+    StepToNextEvent,
+
+    /// Adds a breakpoint which pauses the simulation.
+    ///
+    /// The simulator replies with [`SimulationUpdate::BreakpointAdded`](crate::from_simulator::SimulationUpdate::BreakpointAdded).
+    AddBreakpoint(Breakpoint),
+
+    /// Removes the breakpoint with the given ID.
+    RemoveBreakpoint(BreakpointId),
+
+    /// Lists all breakpoints.
+    ///
+    /// The simulator replies with [`SimulationUpdate::Breakpoints`](crate::from_simulator::SimulationUpdate::Breakpoints).
+    ListBreakpoints,
+
+    /// Pauses the simulation after matching assertion and report statements, replacing the previous setting;
+    /// `None` keeps the simulation running on all reports.
+    ///
+    /// The simulator replies with [`CommandError::InvalidPattern`] if a regular expression can't be compiled.
+    BreakOnReport(Option<ReportBreakpoint>),
+
+    /// Overrides the value of a scalar signal element until it is [released](Self::Release),
+    /// like VHDL-2008's `force`.
+    ///
+    /// Use [`Command::force`] to check the value against the element's type.
+    Force {
+        element: SignalElementId,
+        value: RawValue,
+        mode: ForceMode,
+    },
+
+    /// Removes a [`Command::Force`] with the same mode, like VHDL-2008's `release`.
+    Release {
+        element: SignalElementId,
+        mode: ForceMode,
+    },
+
+    /// Writes a value to a scalar signal element once; the element's drivers may change it again.
+    ///
+    /// Use [`Command::deposit`] to check the value against the element's type.
+    Deposit {
+        element: SignalElementId,
+        value: RawValue,
+    },
+
+    /// Lists all active forces.
+    ///
+    /// The simulator replies with [`SimulationUpdate::Forces`](crate::from_simulator::SimulationUpdate::Forces).
+    ListForces,
+}
+
+impl Command {
+    /// Creates a [`Command::Force`] after checking the value against the element's type.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CommandError::UnknownSignals`] for an unknown element,
+    /// and [`CommandError::InvalidValue`] if the value doesn't fit the element's type.
+    pub fn force(
+        index: &HierarchyIndex<'_>,
+        element: SignalElementId,
+        value: Value,
+        mode: ForceMode,
+    ) -> Result<Self, CommandError> {
+        Ok(Command::Force {
+            element,
+            value: encode_value(index, element, value)?,
+            mode,
+        })
+    }
+
+    /// Creates a [`Command::Deposit`] after checking the value against the element's type.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Command::force`].
+    pub fn deposit(
+        index: &HierarchyIndex<'_>,
+        element: SignalElementId,
+        value: Value,
+    ) -> Result<Self, CommandError> {
+        Ok(Command::Deposit {
+            element,
+            value: encode_value(index, element, value)?,
+        })
+    }
+}
+
+fn encode_value(
+    index: &HierarchyIndex<'_>,
+    element: SignalElementId,
+    value: Value,
+) -> Result<RawValue, CommandError> {
+    let typ = index
+        .signal(element.signal_id)
+        .and_then(|signal| signal.typ.element_type(element.element_index))
+        .ok_or_else(|| CommandError::UnknownSignals(vec![element]))?;
+    typ.encode(value)
+        .map_err(|error| CommandError::InvalidValue(error.to_string()))
+}
+
+/// Which value of a signal a [`Command::Force`] overrides, like VHDL-2008's force modes.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ForceMode {
+    /// The driving value, which is seen outside of a port (`force out`).
+    Driving,
+    /// The effective value, which is read by processes (`force in`).
+    Effective,
+}
+
+/// An active [`Command::Force`].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ForcedValue {
+    pub element: SignalElementId,
+    pub value: RawValue,
+    pub mode: ForceMode,
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
@@ -34,3 +229,88 @@ pub enum RunUntil {
     /// Run for the given duration.
     ForTime { duration: PhysicalTime },
 }
+
+impl RunUntil {
+    /// Rounds the deadline or duration up to a multiple of the simulator's time resolution,
+    /// so the simulation runs at least as long as requested.
+    #[must_use]
+    pub const fn snap_to(self, resolution: PhysicalTime) -> Self {
+        match self {
+            RunUntil::UntilEnd => RunUntil::UntilEnd,
+            RunUntil::UntilTime { deadline } => RunUntil::UntilTime {
+                deadline: deadline.ceil_to(resolution),
+            },
+            RunUntil::ForTime { duration } => RunUntil::ForTime {
+                duration: duration.ceil_to(resolution),
+            },
+        }
+    }
+
+    /// Checks that the deadline or duration is a multiple of the simulator's time resolution.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CommandError::UnalignedTime`] for an unaligned deadline or duration.
+    pub const fn validate(&self, resolution: PhysicalTime) -> Result<(), CommandError> {
+        match *self {
+            RunUntil::UntilTime { deadline: time } | RunUntil::ForTime { duration: time }
+                if !time.is_aligned_to(resolution) =>
+            {
+                Err(CommandError::UnalignedTime { time, resolution })
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::design_hierarchy::Direction;
+    use crate::design_hierarchy::SignalType;
+    use crate::test_utils::hierarchy;
+    use crate::test_utils::module;
+    use crate::test_utils::signal;
+    use crate::test_utils::signal_id;
+
+    #[test]
+    fn checks_forced_values_against_element_type() {
+        let count = SignalType::Integer {
+            min: 0,
+            max: 15,
+            direction: Direction::To,
+        };
+        let hierarchy = hierarchy(vec![module("tb", vec![], vec![signal("count", 1, count)])]);
+        let signal_id = signal_id(1);
+        let index = HierarchyIndex::new(&hierarchy);
+        let element = SignalElementId::new_scalar(signal_id);
+
+        let command =
+            Command::force(&index, element, Value::Integer(7), ForceMode::Effective).unwrap();
+        assert!(matches!(
+            command,
+            Command::Force {
+                value: RawValue(7),
+                mode: ForceMode::Effective,
+                ..
+            }
+        ));
+        assert!(matches!(
+            Command::deposit(&index, element, Value::Integer(16)),
+            Err(CommandError::InvalidValue(_))
+        ));
+        assert!(matches!(
+            Command::deposit(&index, element, Value::Real(1.0)),
+            Err(CommandError::InvalidValue(_))
+        ));
+        assert_eq!(
+            Command::deposit(
+                &index,
+                SignalElementId::new(signal_id, 1),
+                Value::Integer(1)
+            )
+            .unwrap_err(),
+            CommandError::UnknownSignals(vec![SignalElementId::new(signal_id, 1)])
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Typed scalar signal values and their conversion from and to [`RawValue`].
+//!
+//! Values are encoded as follows:
+//! - [`SignalType::Bit`]: `0` or `1`
+//! - [`SignalType::Logic`]: the [`Logic`] discriminant
+//! - [`SignalType::Integer`]: the two's complement bit pattern of the `i64`
+//! - [`SignalType::Real`]: the IEEE 754 bit pattern of the `f64`
+//! - [`SignalType::Enumeration`]: the zero-based index into the enumeration names
+//!
+//! Use [`SignalType::decode`] and [`SignalType::encode`] to convert between both representations.
+
+use std::error::Error;
+use std::fmt;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::Logic;
+#[cfg(doc)]
+use crate::design_hierarchy::SignalType;
+use crate::from_simulator::RawValue;
+
+/// A decoded scalar signal value.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    Bit(bool),
+    Logic(Logic),
+    Integer(i64),
+    Real(f64),
+    /// Zero-based index into the [enumeration names](SignalType::Enumeration::names).
+    Enumeration(u32),
+}
+
+impl From<Value> for RawValue {
+    /// Encodes the value without checking it against a signal type.
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Bit(bit) => RawValue(u64::from(bit)),
+            Value::Logic(logic) => RawValue(logic as u64),
+            Value::Integer(integer) => RawValue(integer as u64),
+            Value::Real(real) => RawValue::from(real),
+            Value::Enumeration(index) => RawValue(u64::from(index)),
+        }
+    }
+}
+
+/// Error when converting between [`RawValue`] and [`Value`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueError {
+    /// The signal type is an array or record, which has no single scalar value.
+    NotScalar,
+    /// The signal type is [unsupported](SignalType::Unsupported).
+    UnsupportedType,
+    /// The raw value doesn't represent a value of the signal type.
+    InvalidEncoding(RawValue),
+    /// The value's kind doesn't match the signal type, e.g. a real value for an integer signal.
+    TypeMismatch(Value),
+    IntegerOutOfRange {
+        value: i64,
+        min: i64,
+        max: i64,
+    },
+    RealOutOfRange {
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    EnumerationIndexOutOfRange {
+        index: u32,
+        count: usize,
+    },
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueError::NotScalar => formatter.write_str("signal type is not scalar"),
+            ValueError::UnsupportedType => formatter.write_str("signal type is not supported"),
+            ValueError::InvalidEncoding(raw) => {
+                write!(
+                    formatter,
+                    "raw value {raw} is not a valid encoding",
+                    raw = raw.0
+                )
+            },
+            ValueError::TypeMismatch(value) => {
+                write!(formatter, "value {value:?} does not match the signal type")
+            },
+            ValueError::IntegerOutOfRange { value, min, max } => {
+                write!(formatter, "integer {value} is not in range {min} to {max}")
+            },
+            ValueError::RealOutOfRange { value, min, max } => {
+                write!(formatter, "real {value} is not in range {min} to {max}")
+            },
+            ValueError::EnumerationIndexOutOfRange { index, count } => write!(
+                formatter,
+                "enumeration index {index} is out of range for {count} literals",
+            ),
+        }
+    }
+}
+
+impl Error for ValueError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::design_hierarchy::Direction;
+    use crate::design_hierarchy::SignalType;
+
+    use super::*;
+
+    #[test]
+    fn round_trips_scalar_values() {
+        let cases = [
+            (SignalType::Bit, Value::Bit(true)),
+            (SignalType::Logic, Value::Logic(Logic::H)),
+            (
+                SignalType::Integer {
+                    min: -8,
+                    max: 7,
+                    direction: Direction::To,
+                },
+                Value::Integer(-3),
+            ),
+            (
+                SignalType::Real {
+                    min: -1.0,
+                    max: 1.0,
+                    direction: Direction::To,
+                },
+                Value::Real(0.25),
+            ),
+            (
+                SignalType::Enumeration {
+                    names: vec!["idle".into(), "busy".into()],
+                },
+                Value::Enumeration(1),
+            ),
+        ];
+        for (typ, value) in cases {
+            let raw = typ.encode(value).unwrap();
+            assert_eq!(typ.decode(raw), Ok(value));
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        let integer = SignalType::Integer {
+            min: 0,
+            max: 9,
+            direction: Direction::To,
+        };
+        assert_eq!(
+            integer.decode(RawValue(-1i64 as u64)),
+            Err(ValueError::IntegerOutOfRange {
+                value: -1,
+                min: 0,
+                max: 9
+            }),
+        );
+        assert_eq!(
+            SignalType::Logic.decode(RawValue(9)),
+            Err(ValueError::InvalidEncoding(RawValue(9))),
+        );
+        let enumeration = SignalType::Enumeration {
+            names: vec!["a".into()],
+        };
+        assert_eq!(
+            enumeration.encode(Value::Enumeration(1)),
+            Err(ValueError::EnumerationIndexOutOfRange { index: 1, count: 1 }),
+        );
+        assert_eq!(
+            SignalType::Bit.encode(Value::Integer(1)),
+            Err(ValueError::TypeMismatch(Value::Integer(1))),
+        );
+    }
+}
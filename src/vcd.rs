@@ -0,0 +1,112 @@
+//! Conversion between simulation data and Value Change Dump (VCD) files as defined by IEEE 1364,
+//! e.g. for viewing waveforms in GTKWave or replaying recorded waveforms.
+//!
+//! [`VcdWriter`] maps the design hierarchy to VCD variables as follows:
+//! - [`Module`](crate::design_hierarchy::Module)s become `module` scopes
+//! - [`SignalType::Bit`](crate::design_hierarchy::SignalType::Bit) and
+//!   [`SignalType::Logic`](crate::design_hierarchy::SignalType::Logic) become 1-bit `wire`s;
+//!   the nine IEEE 1164 values are mapped to the four VCD states
+//! - arrays of bits or logic values become vector `wire`s, indexed like the VHDL array
+//! - [`SignalType::Integer`](crate::design_hierarchy::SignalType::Integer) becomes a 64-bit `integer`
+//! - [`SignalType::Enumeration`](crate::design_hierarchy::SignalType::Enumeration) becomes a 32-bit
+//!   `integer` holding the literal's index
+//! - [`SignalType::Real`](crate::design_hierarchy::SignalType::Real) becomes a `real`
+//! - other arrays are split into one variable per element, named like `data(3)`
+//! - records become `begin` scopes containing their fields
+//!
+//! Unsupported signal types are skipped.
+//! The VCD timescale is the hierarchy's [time resolution](crate::design_hierarchy::DesignHierarchy::time_resolution)
+//! if VCD can express it (1, 10 or 100 fs to s), and 1 fs otherwise;
+//! times which are not a multiple of the timescale are rounded down.
+//!
+//! [`VcdReader`] maps VCD variables back to signals as described in its documentation.
+
+mod reader;
+mod writer;
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+pub use reader::DEFAULT_CHUNK_SIZE;
+pub use reader::VcdReader;
+pub use writer::DeltaCycles;
+pub use writer::MAX_VECTOR_WIDTH;
+pub use writer::VcdOptions;
+pub use writer::VcdWriter;
+
+use crate::Logic;
+
+/// Error when reading a VCD file.
+#[derive(Debug)]
+pub enum VcdError {
+    Io(io::Error),
+    Syntax { line: usize, message: String },
+}
+
+impl fmt::Display for VcdError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VcdError::Io(error) => write!(formatter, "I/O error: {error}"),
+            VcdError::Syntax { line, message } => {
+                write!(formatter, "syntax error in line {line}: {message}")
+            },
+        }
+    }
+}
+
+impl Error for VcdError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            VcdError::Io(error) => Some(error),
+            VcdError::Syntax { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for VcdError {
+    fn from(error: io::Error) -> Self {
+        VcdError::Io(error)
+    }
+}
+
+/// Returns the `index`-th VCD identifier code, using the printable ASCII characters `!` to `~`.
+fn identifier_code(mut index: usize) -> String {
+    const FIRST: u8 = b'!';
+    const COUNT: usize = (b'~' - b'!' + 1) as usize;
+
+    let mut code = String::new();
+    loop {
+        code.push(char::from(FIRST + (index % COUNT) as u8));
+        index /= COUNT;
+        if index == 0 {
+            return code;
+        }
+        index -= 1;
+    }
+}
+
+/// Maps a nine-valued IEEE 1164 value to one of the four VCD states.
+const fn logic_to_vcd(logic: Logic) -> char {
+    match logic {
+        Logic::Zero | Logic::L => '0',
+        Logic::One | Logic::H => '1',
+        Logic::Z => 'z',
+        Logic::U | Logic::X | Logic::W | Logic::DontCare => 'x',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn identifier_codes_are_unique() {
+        let codes: HashSet<String> = (0..20_000).map(identifier_code).collect();
+        assert_eq!(codes.len(), 20_000);
+        assert_eq!(identifier_code(0), "!");
+        assert_eq!(identifier_code(94), "!!");
+    }
+}
@@ -0,0 +1,649 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::mem;
+use std::num::NonZeroU32;
+
+use compact_str::CompactString;
+
+use crate::Logic;
+use crate::SimulationId;
+use crate::design_hierarchy::DesignHierarchy;
+use crate::design_hierarchy::Direction;
+use crate::design_hierarchy::Module;
+use crate::design_hierarchy::ModuleKind;
+use crate::design_hierarchy::Signal;
+use crate::design_hierarchy::SignalElementId;
+use crate::design_hierarchy::SignalInstanceId;
+use crate::design_hierarchy::SignalType;
+use crate::from_simulator::Event;
+use crate::from_simulator::EventsUpdate;
+use crate::from_simulator::RawValue;
+use crate::from_simulator::SignalEvents;
+use crate::time::Delta;
+use crate::time::LogicalTime;
+use crate::time::PhysicalTime;
+
+use super::VcdError;
+
+/// Default minimum number of events per [`EventsUpdate`] returned by [`VcdReader`].
+pub const DEFAULT_CHUNK_SIZE: usize = 100_000;
+
+/// Reads a VCD file as a [`DesignHierarchy`] and a stream of [`EventsUpdate`]s.
+///
+/// The header is parsed on construction. Iterating over the reader yields the value changes in chunks
+/// of whole timestamps, each containing at least [`DEFAULT_CHUNK_SIZE`] events (except for the last one)
+/// unless changed with [`Self::with_chunk_size`].
+/// Consecutive chunks have adjacent time ranges, and all events are in delta cycle 0.
+///
+/// Signal IDs are assigned sequentially in declaration order, starting at 1.
+/// Every VCD variable becomes one signal:
+/// - `real` and `realtime` variables become [`SignalType::Real`]
+/// - 1-bit variables without a range become [`SignalType::Logic`]
+/// - all other variables become arrays of [`SignalType::Logic`], indexed like the VCD range
+/// - `string` variables become [`SignalType::Unsupported`] and have no events
+///
+/// Scopes become [design entities](ModuleKind::DesignEntity) with the scope name as entity name
+/// and the scope type (like `module` or `begin`) as architecture name.
+#[derive(Debug)]
+pub struct VcdReader<R: BufRead> {
+    tokens: Tokenizer<R>,
+    hierarchy: DesignHierarchy,
+    variables: HashMap<CompactString, Vec<Variable>>,
+    /// Femtoseconds per VCD time unit.
+    timescale: u64,
+    chunk_size: usize,
+    time: PhysicalTime,
+    chunk: Chunk,
+    finished: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Variable {
+    signal_id: SignalInstanceId,
+    kind: VariableKind,
+    /// The current value of every element, used to only report changed elements of vectors.
+    values: Vec<Option<RawValue>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VariableKind {
+    Logic,
+    Real,
+    Unsupported,
+}
+
+#[derive(Debug, Default)]
+struct Chunk {
+    start: LogicalTime,
+    signals: Vec<SignalEvents>,
+    signal_indices: HashMap<SignalElementId, usize>,
+    event_count: usize,
+}
+
+impl<R: BufRead> VcdReader<R> {
+    /// Parses the VCD header up to `$enddefinitions`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails or the header is malformed.
+    pub fn new(reader: R) -> Result<Self, VcdError> {
+        let mut vcd = Self {
+            tokens: Tokenizer::new(reader),
+            hierarchy: DesignHierarchy {
+                simulation_id: SimulationId::new_random(),
+                name: None,
+                start_time: 0.0,
+                time_resolution: PhysicalTime(1),
+                root_modules: vec![],
+            },
+            variables: HashMap::new(),
+            timescale: 1,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            time: PhysicalTime::ZERO,
+            chunk: Chunk::default(),
+            finished: false,
+        };
+        vcd.read_header()?;
+        vcd.hierarchy.time_resolution = PhysicalTime(vcd.timescale);
+        Ok(vcd)
+    }
+
+    /// Sets the minimum number of events per chunk.
+    #[must_use]
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    pub const fn hierarchy(&self) -> &DesignHierarchy {
+        &self.hierarchy
+    }
+
+    /// Returns the VCD time unit in femtoseconds.
+    pub const fn timescale(&self) -> PhysicalTime {
+        PhysicalTime(self.timescale)
+    }
+
+    fn read_header(&mut self) -> Result<(), VcdError> {
+        let mut scopes: Vec<Module> = vec![];
+        // variables declared outside of any scope
+        let mut orphans: Vec<Signal> = vec![];
+        let mut next_id = NonZeroU32::MIN;
+        loop {
+            let keyword = self.tokens.expect_token()?;
+            match keyword.as_str() {
+                "$enddefinitions" => {
+                    self.tokens.skip_until_end()?;
+                    break;
+                },
+                "$timescale" => {
+                    let text = self.tokens.read_until_end()?.concat();
+                    self.timescale = parse_timescale(&text)
+                        .ok_or_else(|| self.tokens.error(format!("invalid timescale {text:?}")))?;
+                },
+                "$scope" => {
+                    let tokens = self.tokens.read_until_end()?;
+                    let [scope_type, name] = tokens.as_slice() else {
+                        return Err(self.tokens.error("expected scope type and name"));
+                    };
+                    scopes.push(Module {
+                        name: Some(name.as_str().into()),
+                        kind: ModuleKind::DesignEntity {
+                            entity: name.as_str().into(),
+                            architecture: scope_type.as_str().into(),
+                        },
+                        submodules: vec![],
+                        signals: vec![],
+                    });
+                },
+                "$upscope" => {
+                    self.tokens.skip_until_end()?;
+                    let module = scopes
+                        .pop()
+                        .ok_or_else(|| self.tokens.error("$upscope without $scope"))?;
+                    match scopes.last_mut() {
+                        Some(parent) => parent.submodules.push(module),
+                        None => self.hierarchy.root_modules.push(module),
+                    }
+                },
+                "$var" => {
+                    let signal = self.read_variable(next_id)?;
+                    next_id = next_id
+                        .checked_add(1)
+                        .ok_or_else(|| self.tokens.error("too many variables"))?;
+                    match scopes.last_mut() {
+                        Some(module) => module.signals.push(signal),
+                        None => orphans.push(signal),
+                    }
+                },
+                _ if keyword.starts_with('$') => self.tokens.skip_until_end()?,
+                _ => return Err(self.tokens.error(format!("unexpected token {keyword:?}"))),
+            }
+        }
+        if !scopes.is_empty() {
+            return Err(self.tokens.error("missing $upscope"));
+        }
+        if !orphans.is_empty() {
+            self.hierarchy.root_modules.insert(
+                0,
+                Module {
+                    name: None,
+                    kind: ModuleKind::Package,
+                    submodules: vec![],
+                    signals: orphans,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn read_variable(&mut self, id: NonZeroU32) -> Result<Signal, VcdError> {
+        let tokens = self.tokens.read_until_end()?;
+        let [var_type, size, code, reference @ ..] = tokens.as_slice() else {
+            return Err(self.tokens.error("incomplete $var declaration"));
+        };
+        let size: u32 = size
+            .parse()
+            .map_err(|_| self.tokens.error(format!("invalid variable size {size:?}")))?;
+        let reference = reference.concat();
+        let (name, range) = match reference.find('[') {
+            Some(bracket) if reference.ends_with(']') => {
+                let range = &reference[bracket + 1..reference.len() - 1];
+                (&reference[..bracket], Some(range))
+            },
+            _ => (reference.as_str(), None),
+        };
+        let signal_id = SignalInstanceId(id);
+
+        let (typ, kind) = match var_type.as_str() {
+            "real" | "realtime" | "shortreal" => (
+                SignalType::Real {
+                    min: f64::MIN,
+                    max: f64::MAX,
+                    direction: Direction::To,
+                },
+                VariableKind::Real,
+            ),
+            "string" => (SignalType::Unsupported, VariableKind::Unsupported),
+            _ if size == 1 && range.is_none_or(|range| !range.contains(':')) => {
+                (SignalType::Logic, VariableKind::Logic)
+            },
+            _ => {
+                let (left, right) = match range.and_then(|range| range.split_once(':')) {
+                    Some((left, right)) => (left.trim().parse().ok(), right.trim().parse().ok()),
+                    None => (i32::try_from(size).ok().map(|size| size - 1), Some(0)),
+                };
+                let (Some(left), Some(right)) = (left, right) else {
+                    return Err(self.tokens.error(format!("invalid range in {reference:?}")));
+                };
+                let direction = if left >= right {
+                    Direction::Downto
+                } else {
+                    Direction::To
+                };
+                (
+                    SignalType::Array {
+                        left,
+                        right,
+                        direction,
+                        element_count: size,
+                        element_type: Box::new(SignalType::Logic),
+                    },
+                    VariableKind::Logic,
+                )
+            },
+        };
+
+        let element_count = typ.element_count() as usize;
+        self.variables
+            .entry(code.as_str().into())
+            .or_default()
+            .push(Variable {
+                signal_id,
+                kind,
+                values: vec![None; element_count],
+            });
+        Ok(Signal {
+            name: name.into(),
+            id: signal_id,
+            typ,
+        })
+    }
+
+    /// Reads value changes until the current chunk is full or the file ends.
+    fn read_chunk(&mut self) -> Result<Option<EventsUpdate>, VcdError> {
+        loop {
+            let Some(token) = self.tokens.next_token()? else {
+                self.finished = true;
+                if self.chunk.signals.is_empty() {
+                    return Ok(None);
+                }
+                let end = LogicalTime::from(self.time) + Delta(1);
+                return Ok(Some(self.take_chunk(end)));
+            };
+            let mut chars = token.chars();
+            match chars.next() {
+                Some('#') => {
+                    let time: u64 = chars
+                        .as_str()
+                        .parse()
+                        .map_err(|_| self.tokens.error(format!("invalid timestamp {token:?}")))?;
+                    let time = time
+                        .checked_mul(self.timescale)
+                        .map(PhysicalTime)
+                        .ok_or_else(|| self.tokens.error("timestamp overflows u64 femtoseconds"))?;
+                    if time < self.time {
+                        return Err(self
+                            .tokens
+                            .error(format!("timestamp {token} goes back in time")));
+                    }
+                    self.time = time;
+                    if self.chunk.event_count >= self.chunk_size {
+                        return Ok(Some(self.take_chunk(time.into())));
+                    }
+                },
+                Some('b' | 'B') => {
+                    let bits = chars.as_str().to_owned();
+                    let code = self.tokens.expect_token()?;
+                    self.change_vector(&code, &bits)?;
+                },
+                Some('r' | 'R') => {
+                    let text = chars.as_str();
+                    let real: f64 = text
+                        .parse()
+                        .map_err(|_| self.tokens.error(format!("invalid real value {text:?}")))?;
+                    let code = self.tokens.expect_token()?;
+                    self.change(&code, |_, _| Some(RawValue::from(real)));
+                },
+                Some('s' | 'S') => {
+                    self.tokens.expect_token()?;
+                },
+                Some(state) if vcd_to_logic(state).is_some() => {
+                    let code = chars.as_str();
+                    let code = if code.is_empty() {
+                        self.tokens.expect_token()?
+                    } else {
+                        code.to_owned()
+                    };
+                    self.change_vector(&code, &state.to_string())?;
+                },
+                // $dumpvars, $dumpall, $dumpon and $dumpoff contain regular value changes
+                Some('$') if token == "$comment" => self.tokens.skip_until_end()?,
+                Some('$') => {},
+                _ => return Err(self.tokens.error(format!("unexpected token {token:?}"))),
+            }
+        }
+    }
+
+    /// Applies a binary value to all variables with the code,
+    /// left-extending it to their width or keeping only its rightmost bits.
+    fn change_vector(&mut self, code: &str, bits: &str) -> Result<(), VcdError> {
+        let mut states = Vec::with_capacity(bits.len());
+        for bit in bits.chars() {
+            let logic = vcd_to_logic(bit)
+                .ok_or_else(|| self.tokens.error(format!("invalid binary value {bits:?}")))?;
+            states.push(logic);
+        }
+        self.change(code, |width, element| {
+            let logic = match (element + states.len()).checked_sub(width) {
+                Some(bit) => states[bit],
+                None => match states.first() {
+                    Some(Logic::One) | None => Logic::Zero,
+                    Some(&first) => first,
+                },
+            };
+            Some(RawValue(logic as u64))
+        });
+        Ok(())
+    }
+
+    /// Records events for the elements of all variables with the code whose values changed.
+    fn change(&mut self, code: &str, value: impl Fn(usize, usize) -> Option<RawValue>) {
+        let Some(variables) = self.variables.get_mut(code) else {
+            return;
+        };
+        let time = LogicalTime::from(self.time);
+        for variable in variables {
+            if variable.kind == VariableKind::Unsupported {
+                continue;
+            }
+            let width = variable.values.len();
+            for (element, current) in variable.values.iter_mut().enumerate() {
+                let Some(new) = value(width, element) else {
+                    continue;
+                };
+                if *current == Some(new) {
+                    continue;
+                }
+                *current = Some(new);
+                let element_id = SignalElementId::new(variable.signal_id, element as u32);
+                let chunk = &mut self.chunk;
+                let index = *chunk.signal_indices.entry(element_id).or_insert_with(|| {
+                    chunk.signals.push(SignalEvents::new(element_id));
+                    chunk.signals.len() - 1
+                });
+                let events = &mut chunk.signals[index].events;
+                match events.last_mut() {
+                    Some(last) if last.time == time => last.value = new,
+                    _ => {
+                        events.push(Event { time, value: new });
+                        chunk.event_count += 1;
+                    },
+                }
+            }
+        }
+    }
+
+    fn take_chunk(&mut self, end: LogicalTime) -> EventsUpdate {
+        let chunk = mem::take(&mut self.chunk);
+        self.chunk.start = end;
+        EventsUpdate {
+            time_range: chunk.start..end,
+            signals: chunk.signals,
+            reports: vec![],
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for VcdReader<R> {
+    type Item = Result<EventsUpdate, VcdError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let chunk = self.read_chunk();
+        if chunk.is_err() {
+            self.finished = true;
+        }
+        chunk.transpose()
+    }
+}
+
+/// Parses a timescale like `10 ns` or `1ps` into femtoseconds.
+fn parse_timescale(text: &str) -> Option<u64> {
+    let split = text.find(|ch: char| !ch.is_ascii_digit())?;
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let unit: u64 = match unit {
+        "fs" => 1,
+        "ps" => 1_000,
+        "ns" => 1_000_000,
+        "us" => 1_000_000_000,
+        "ms" => 1_000_000_000_000,
+        "s" => 1_000_000_000_000_000,
+        _ => return None,
+    };
+    match number {
+        1 | 10 | 100 => Some(number * unit),
+        _ => None,
+    }
+}
+
+/// Maps a VCD state character to a logic value, including the nine-valued GTKWave extension.
+const fn vcd_to_logic(state: char) -> Option<Logic> {
+    match state {
+        '0' => Some(Logic::Zero),
+        '1' => Some(Logic::One),
+        'x' | 'X' => Some(Logic::X),
+        'z' | 'Z' => Some(Logic::Z),
+        'u' | 'U' => Some(Logic::U),
+        'w' | 'W' => Some(Logic::W),
+        'l' | 'L' => Some(Logic::L),
+        'h' | 'H' => Some(Logic::H),
+        '-' => Some(Logic::DontCare),
+        _ => None,
+    }
+}
+
+/// Splits the input into whitespace-separated tokens.
+#[derive(Debug)]
+struct Tokenizer<R> {
+    reader: R,
+    line: String,
+    line_number: usize,
+    tokens: VecDeque<String>,
+}
+
+impl<R: BufRead> Tokenizer<R> {
+    const fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            line_number: 0,
+            tokens: VecDeque::new(),
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<String>, VcdError> {
+        while self.tokens.is_empty() {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            self.tokens
+                .extend(self.line.split_whitespace().map(str::to_owned));
+        }
+        Ok(self.tokens.pop_front())
+    }
+
+    fn expect_token(&mut self) -> Result<String, VcdError> {
+        self.next_token()?
+            .ok_or_else(|| self.error("unexpected end of file"))
+    }
+
+    /// Returns all tokens up to the next `$end`, which is consumed.
+    fn read_until_end(&mut self) -> Result<Vec<String>, VcdError> {
+        let mut tokens = vec![];
+        loop {
+            let token = self.expect_token()?;
+            if token == "$end" {
+                return Ok(tokens);
+            }
+            tokens.push(token);
+        }
+    }
+
+    fn skip_until_end(&mut self) -> Result<(), VcdError> {
+        while self.expect_token()? != "$end" {}
+        Ok(())
+    }
+
+    fn error(&self, message: impl Into<String>) -> VcdError {
+        VcdError::Syntax {
+            line: self.line_number,
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VCD: &str = "\
+$date today $end
+$timescale 10 ps $end
+$scope module tb $end
+$var wire 1 ! clk $end
+$scope module dut $end
+$var reg 4 \" data [3:0] $end
+$var real 64 # level $end
+$var wire 1 ! clk_alias $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+$dumpvars
+0!
+bx \"
+r0.5 #
+$end
+#5
+1!
+b10 \"
+#10
+0!
+b110 \"
+";
+
+    #[test]
+    fn reads_hierarchy_and_events() {
+        let mut reader = VcdReader::new(VCD.as_bytes()).unwrap().with_chunk_size(6);
+        assert_eq!(reader.timescale(), PhysicalTime(10_000));
+        assert_eq!(reader.hierarchy().time_resolution, PhysicalTime(10_000));
+
+        let hierarchy = reader.hierarchy().clone();
+        let tb = &hierarchy.root_modules[0];
+        assert_eq!(tb.name.as_deref(), Some("tb"));
+        assert_eq!(tb.signals[0].name, "clk");
+        let dut = &tb.submodules[0];
+        assert!(matches!(
+            dut.signals[0].typ,
+            SignalType::Array {
+                left: 3,
+                right: 0,
+                element_count: 4,
+                ..
+            }
+        ));
+        assert!(matches!(dut.signals[1].typ, SignalType::Real { .. }));
+
+        let first = reader.next().unwrap().unwrap();
+        assert_eq!(
+            first.time_range,
+            LogicalTime::ZERO..LogicalTime::from(50_000)
+        );
+        // clk and its alias, four data bits, level
+        assert_eq!(first.signals.len(), 7);
+
+        let second = reader.next().unwrap().unwrap();
+        assert_eq!(second.time_range.start, LogicalTime::from(50_000));
+        let data_events = |update: &EventsUpdate, element_index| {
+            let element_id = SignalElementId::new(dut.signals[0].id, element_index);
+            update
+                .signals
+                .iter()
+                .find(|signal| signal.element_id == element_id)
+                .map(|signal| {
+                    signal
+                        .events
+                        .iter()
+                        .map(|event| event.value.0)
+                        .collect::<Vec<_>>()
+                })
+        };
+        // b10 is left-extended with zeros to 0010, b110 to 0110
+        let zero = Logic::Zero as u64;
+        let one = Logic::One as u64;
+        assert_eq!(data_events(&second, 0), Some(vec![zero]));
+        assert_eq!(data_events(&second, 1), Some(vec![zero]));
+        assert_eq!(data_events(&second, 2), Some(vec![one]));
+
+        let third = reader.next().unwrap().unwrap();
+        assert_eq!(
+            third.time_range,
+            LogicalTime::from(100_000)..LogicalTime::from(100_000) + Delta(1),
+        );
+        assert_eq!(data_events(&third, 0), None);
+        assert_eq!(data_events(&third, 1), Some(vec![one]));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn truncates_over_wide_values_from_the_left() {
+        let vcd = "\
+$scope module tb $end
+$var wire 2 ! data [1:0] $end
+$upscope $end
+$enddefinitions $end
+#0
+b1101 !
+";
+        let mut reader = VcdReader::new(vcd.as_bytes()).unwrap();
+        let data = reader.hierarchy().root_modules[0].signals[0].id;
+        let update = reader.next().unwrap().unwrap();
+        let values: Vec<_> = (0..2)
+            .map(|element_index| {
+                let element_id = SignalElementId::new(data, element_index);
+                let signal = update
+                    .signals
+                    .iter()
+                    .find(|signal| signal.element_id == element_id)
+                    .unwrap();
+                signal.events[0].value.0
+            })
+            .collect();
+        assert_eq!(values, [Logic::Zero as u64, Logic::One as u64]);
+    }
+
+    #[test]
+    fn parses_timescales() {
+        assert_eq!(parse_timescale("1fs"), Some(1));
+        assert_eq!(parse_timescale("100us"), Some(100_000_000_000));
+        assert_eq!(parse_timescale("2ns"), None);
+        assert_eq!(parse_timescale("1 min"), None);
+    }
+}
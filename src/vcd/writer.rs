@@ -0,0 +1,602 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+use crate::Logic;
+use crate::design_hierarchy::DesignHierarchy;
+use crate::design_hierarchy::Module;
+use crate::design_hierarchy::SignalElementId;
+use crate::design_hierarchy::SignalInstanceId;
+use crate::design_hierarchy::SignalType;
+use crate::from_simulator::EventsUpdate;
+use crate::from_simulator::RawValue;
+use crate::time::LogicalTime;
+use crate::time::PhysicalTime;
+use crate::time::TimeUnit;
+
+use super::identifier_code;
+use super::logic_to_vcd;
+
+/// Maximum width of a vector variable, since the writer keeps the state of every bit in memory.
+pub const MAX_VECTOR_WIDTH: u32 = 1 << 24;
+
+/// How events in delta cycles are written, since VCD has no notion of delta cycles.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeltaCycles {
+    /// Only the value at the end of each time step is written.
+    #[default]
+    Collapse,
+    /// Every delta cycle gets its own timestamp.
+    ///
+    /// Each written delta cycle after the first of a time step is written one timescale unit
+    /// after the previous timestamp, and shifts all later timestamps by one unit,
+    /// so time steps never merge.
+    Expand,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VcdOptions {
+    pub delta_cycles: DeltaCycles,
+}
+
+/// Writes a VCD file from a [`DesignHierarchy`] and a sequence of [`EventsUpdate`]s.
+///
+/// The header is written on construction.
+/// Events must be passed in chronological order of their updates; events within an update may be unordered.
+#[derive(Debug)]
+pub struct VcdWriter<W: Write> {
+    writer: W,
+    options: VcdOptions,
+    variables: Vec<Variable>,
+    targets: HashMap<SignalElementId, Target>,
+    /// Length of a VCD time unit in femtoseconds.
+    timescale: u64,
+    /// The last timestamp written to the file, in VCD time units.
+    time: u64,
+    /// Number of VCD time units inserted for [expanded](DeltaCycles::Expand) delta cycles so far.
+    offset: u64,
+    end_time: PhysicalTime,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Target {
+    variable: usize,
+    bit: u32,
+    kind: ElementKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ElementKind {
+    Bit,
+    Logic,
+    Integer,
+    Real,
+}
+
+#[derive(Debug)]
+struct Variable {
+    code: String,
+    value: VariableValue,
+    changed: bool,
+}
+
+#[derive(Debug)]
+enum VariableValue {
+    /// One VCD state character per bit, leftmost first.
+    Bits(Vec<u8>),
+    /// `None` until the first event, written as `bx`.
+    Integer(Option<u64>),
+    /// `None` until the first event; VCD has no unknown real value, so nothing is written.
+    Real(Option<f64>),
+}
+
+impl<W: Write> VcdWriter<W> {
+    /// Creates a writer and writes the VCD header for the hierarchy.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error of the underlying writer, or an error of kind
+    /// [`InvalidInput`](io::ErrorKind::InvalidInput) if an array of bits is wider than
+    /// [`MAX_VECTOR_WIDTH`] or the element indices of a signal exceed `u32`.
+    pub fn new(writer: W, hierarchy: &DesignHierarchy, options: VcdOptions) -> io::Result<Self> {
+        let mut vcd = Self {
+            writer,
+            options,
+            variables: vec![],
+            targets: HashMap::new(),
+            timescale: 1,
+            time: 0,
+            offset: 0,
+            end_time: PhysicalTime::ZERO,
+        };
+        vcd.write_header(hierarchy)?;
+        Ok(vcd)
+    }
+
+    fn write_header(&mut self, hierarchy: &DesignHierarchy) -> io::Result<()> {
+        writeln!(
+            self.writer,
+            "$version {name} {version} $end",
+            name = env!("CARGO_PKG_NAME"),
+            version = env!("CARGO_PKG_VERSION"),
+        )?;
+        if let Some(name) = &hierarchy.name {
+            writeln!(self.writer, "$comment {name} $end")?;
+        }
+        let timescale = match vcd_timescale(hierarchy.time_resolution) {
+            Some(timescale) => {
+                self.timescale = hierarchy.time_resolution.0;
+                timescale
+            },
+            None => "1 fs".into(),
+        };
+        writeln!(self.writer, "$timescale {timescale} $end")?;
+        for module in &hierarchy.root_modules {
+            self.write_module(module)?;
+        }
+        writeln!(self.writer, "$enddefinitions $end")?;
+
+        writeln!(self.writer, "#0")?;
+        writeln!(self.writer, "$dumpvars")?;
+        for index in 0..self.variables.len() {
+            self.write_value(index)?;
+        }
+        writeln!(self.writer, "$end")
+    }
+
+    fn write_module(&mut self, module: &Module) -> io::Result<()> {
+        if let Some(name) = &module.name {
+            writeln!(self.writer, "$scope module {name} $end")?;
+        }
+        for signal in &module.signals {
+            self.write_variables(&signal.name, &signal.typ, signal.id, 0)?;
+        }
+        for submodule in &module.submodules {
+            self.write_module(submodule)?;
+        }
+        if module.name.is_some() {
+            writeln!(self.writer, "$upscope $end")?;
+        }
+        Ok(())
+    }
+
+    /// Declares the variables for a signal or a part of it, starting at `element_offset`.
+    fn write_variables(
+        &mut self,
+        name: &str,
+        typ: &SignalType,
+        signal_id: SignalInstanceId,
+        element_offset: u32,
+    ) -> io::Result<()> {
+        match typ {
+            SignalType::Bit | SignalType::Logic => {
+                let kind = if matches!(typ, SignalType::Bit) {
+                    ElementKind::Bit
+                } else {
+                    ElementKind::Logic
+                };
+                let code = self.add_variable(VariableValue::Bits(vec![b'x']));
+                self.add_targets(signal_id, element_offset, 1, kind);
+                writeln!(self.writer, "$var wire 1 {code} {name} $end")
+            },
+            SignalType::Integer { .. } | SignalType::Enumeration { .. } => {
+                let width = if matches!(typ, SignalType::Integer { .. }) {
+                    64
+                } else {
+                    32
+                };
+                let code = self.add_variable(VariableValue::Integer(None));
+                self.add_targets(signal_id, element_offset, 1, ElementKind::Integer);
+                writeln!(self.writer, "$var integer {width} {code} {name} $end")
+            },
+            SignalType::Real { .. } => {
+                let code = self.add_variable(VariableValue::Real(None));
+                self.add_targets(signal_id, element_offset, 1, ElementKind::Real);
+                writeln!(self.writer, "$var real 64 {code} {name} $end")
+            },
+            SignalType::Array {
+                left,
+                right,
+                direction,
+                element_type,
+                ..
+            } => {
+                let length = direction.length_for(*left, *right);
+                if let SignalType::Bit | SignalType::Logic = **element_type {
+                    if length == 0 {
+                        return Ok(());
+                    }
+                    if length > MAX_VECTOR_WIDTH {
+                        return Err(invalid_signal(
+                            name,
+                            &format!("is wider than {MAX_VECTOR_WIDTH} bits"),
+                        ));
+                    }
+                    if element_offset.checked_add(length - 1).is_none() {
+                        return Err(invalid_signal(name, ELEMENT_OVERFLOW));
+                    }
+                    let kind = if matches!(**element_type, SignalType::Bit) {
+                        ElementKind::Bit
+                    } else {
+                        ElementKind::Logic
+                    };
+                    let code = self.add_variable(VariableValue::Bits(vec![b'x'; length as usize]));
+                    self.add_targets(signal_id, element_offset, length, kind);
+                    return writeln!(
+                        self.writer,
+                        "$var wire {length} {code} {name} [{left}:{right}] $end",
+                    );
+                }
+                let stride = element_type.element_count();
+                for position in 0..length {
+                    let index = direction.index_at(*left, position);
+                    let name = format!("{name}({index})");
+                    let offset = position
+                        .checked_mul(stride)
+                        .and_then(|offset| element_offset.checked_add(offset))
+                        .ok_or_else(|| invalid_signal(&name, ELEMENT_OVERFLOW))?;
+                    self.write_variables(&name, element_type, signal_id, offset)?;
+                }
+                Ok(())
+            },
+            SignalType::Record { fields, .. } => {
+                writeln!(self.writer, "$scope begin {name} $end")?;
+                for field in fields {
+                    let offset = element_offset
+                        .checked_add(field.element_offset)
+                        .ok_or_else(|| invalid_signal(&field.name, ELEMENT_OVERFLOW))?;
+                    self.write_variables(&field.name, &field.typ, signal_id, offset)?;
+                }
+                writeln!(self.writer, "$upscope $end")
+            },
+            SignalType::Unsupported => Ok(()),
+        }
+    }
+
+    fn add_variable(&mut self, value: VariableValue) -> String {
+        let code = identifier_code(self.variables.len());
+        self.variables.push(Variable {
+            code: code.clone(),
+            value,
+            changed: false,
+        });
+        code
+    }
+
+    /// Maps `count` consecutive elements to the bits of the last added variable.
+    fn add_targets(
+        &mut self,
+        signal_id: SignalInstanceId,
+        element_offset: u32,
+        count: u32,
+        kind: ElementKind,
+    ) {
+        let variable = self.variables.len() - 1;
+        for bit in 0..count {
+            self.targets.insert(
+                SignalElementId::new(signal_id, element_offset + bit),
+                Target {
+                    variable,
+                    bit,
+                    kind,
+                },
+            );
+        }
+    }
+
+    /// Writes all events of an update.
+    ///
+    /// Events of elements which are not part of the hierarchy are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error of the underlying writer.
+    pub fn write_events(&mut self, update: &EventsUpdate) -> io::Result<()> {
+        let mut events: Vec<(LogicalTime, Target, RawValue)> = update
+            .signals
+            .iter()
+            .filter_map(|signal| Some((self.targets.get(&signal.element_id)?, &signal.events)))
+            .flat_map(|(&target, events)| {
+                events
+                    .iter()
+                    .map(move |event| (event.time, target, event.value))
+            })
+            .collect();
+        events.sort_by_key(|&(time, ..)| time);
+
+        let mut start = 0;
+        while start < events.len() {
+            let group_time = events[start].0;
+            let end = events[start..]
+                .iter()
+                .position(|&(time, ..)| match self.options.delta_cycles {
+                    DeltaCycles::Collapse => time.physical != group_time.physical,
+                    DeltaCycles::Expand => time != group_time,
+                })
+                .map_or(events.len(), |offset| start + offset);
+            for &(_, target, value) in &events[start..end] {
+                self.apply(target, value);
+            }
+            self.write_time_step(group_time)?;
+            start = end;
+        }
+        self.end_time = self.end_time.max(update.time_range.end.physical);
+        Ok(())
+    }
+
+    fn apply(&mut self, target: Target, value: RawValue) {
+        let variable = &mut self.variables[target.variable];
+        let changed = match (&mut variable.value, target.kind) {
+            (VariableValue::Bits(bits), ElementKind::Bit | ElementKind::Logic) => {
+                let state = if target.kind == ElementKind::Bit {
+                    if value.0 == 0 { b'0' } else { b'1' }
+                } else {
+                    u8::try_from(value.0)
+                        .ok()
+                        .and_then(|discriminant| Logic::try_from(discriminant).ok())
+                        .map_or(b'x', |logic| logic_to_vcd(logic) as u8)
+                };
+                let bit = &mut bits[target.bit as usize];
+                std::mem::replace(bit, state) != state
+            },
+            (VariableValue::Integer(integer), _) => integer.replace(value.0) != Some(value.0),
+            (VariableValue::Real(real), _) => {
+                let new = f64::from_bits(value.0);
+                real.replace(new).map(f64::to_bits) != Some(value.0)
+            },
+            (VariableValue::Bits(_), _) => false,
+        };
+        variable.changed |= changed;
+    }
+
+    fn write_time_step(&mut self, time: LogicalTime) -> io::Result<()> {
+        if !self.variables.iter().any(|variable| variable.changed) {
+            return Ok(());
+        }
+        if self.options.delta_cycles == DeltaCycles::Expand && time.delta.0 > 0 {
+            self.offset += 1;
+        }
+        let timestamp = time.physical.0 / self.timescale + self.offset;
+        if timestamp > self.time {
+            writeln!(self.writer, "#{timestamp}")?;
+            self.time = timestamp;
+        }
+        for index in 0..self.variables.len() {
+            if self.variables[index].changed {
+                self.write_value(index)?;
+                self.variables[index].changed = false;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_value(&mut self, index: usize) -> io::Result<()> {
+        let Variable { code, value, .. } = &self.variables[index];
+        match value {
+            VariableValue::Bits(bits) if bits.len() == 1 => {
+                writeln!(self.writer, "{state}{code}", state = char::from(bits[0]))
+            },
+            VariableValue::Bits(bits) => {
+                self.writer.write_all(b"b")?;
+                self.writer.write_all(bits)?;
+                writeln!(self.writer, " {code}")
+            },
+            VariableValue::Integer(Some(integer)) => writeln!(self.writer, "b{integer:b} {code}"),
+            VariableValue::Integer(None) => writeln!(self.writer, "bx {code}"),
+            VariableValue::Real(Some(real)) => writeln!(self.writer, "r{real:e} {code}"),
+            VariableValue::Real(None) => Ok(()),
+        }
+    }
+
+    /// Writes a final timestamp for the end of the last update's time range and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error of the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let end = self.end_time.0 / self.timescale + self.offset;
+        if end > self.time {
+            writeln!(self.writer, "#{end}")?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Returns the VCD timescale for a time resolution, if VCD can express it.
+fn vcd_timescale(resolution: PhysicalTime) -> Option<String> {
+    let units = [
+        TimeUnit::Fs,
+        TimeUnit::Ps,
+        TimeUnit::Ns,
+        TimeUnit::Us,
+        TimeUnit::Ms,
+        TimeUnit::Sec,
+    ];
+    units.into_iter().find_map(|unit| {
+        let factor = [1, 10, 100]
+            .into_iter()
+            .find(|factor| factor * unit.femtoseconds() == resolution.0)?;
+        let symbol = if unit == TimeUnit::Sec {
+            "s"
+        } else {
+            unit.symbol()
+        };
+        Some(format!("{factor} {symbol}"))
+    })
+}
+
+const ELEMENT_OVERFLOW: &str = "has element indices beyond u32";
+
+/// Returns the error for a signal which can't be written.
+fn invalid_signal(name: &str, problem: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("signal {name} {problem}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::design_hierarchy::Direction;
+    use crate::design_hierarchy::RecordField;
+    use crate::from_simulator::Event;
+    use crate::from_simulator::SignalEvents;
+    use crate::test_utils::logic_vector;
+    use crate::test_utils::module;
+    use crate::test_utils::signal;
+
+    fn hierarchy() -> DesignHierarchy {
+        let signals = vec![
+            signal("clk", 1, SignalType::Logic),
+            signal("data", 2, logic_vector(1, 0)),
+        ];
+        crate::test_utils::hierarchy(vec![module("tb", vec![], signals)])
+    }
+
+    fn events(element_id: SignalElementId, events: &[((u64, u64), Logic)]) -> SignalEvents {
+        SignalEvents {
+            element_id,
+            events: events
+                .iter()
+                .map(|&(time, logic)| Event {
+                    time: time.into(),
+                    value: RawValue(logic as u64),
+                })
+                .collect(),
+        }
+    }
+
+    fn write(options: VcdOptions) -> String {
+        let hierarchy = hierarchy();
+        let clk = SignalElementId::new_scalar(hierarchy.root_modules[0].signals[0].id);
+        let data = hierarchy.root_modules[0].signals[1].id;
+        let update = EventsUpdate {
+            time_range: LogicalTime::ZERO..LogicalTime::from(20),
+            signals: vec![
+                events(clk, &[((0, 0), Logic::Zero), ((10, 0), Logic::One)]),
+                events(
+                    SignalElementId::new(data, 0),
+                    &[((10, 0), Logic::Zero), ((10, 1), Logic::One)],
+                ),
+                events(SignalElementId::new(data, 1), &[((10, 1), Logic::H)]),
+            ],
+            reports: vec![],
+        };
+        let mut vcd = VcdWriter::new(vec![], &hierarchy, options).unwrap();
+        vcd.write_events(&update).unwrap();
+        String::from_utf8(vcd.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn writes_header_and_collapsed_changes() {
+        let vcd = write(VcdOptions::default());
+        assert!(vcd.contains("$scope module tb $end\n$var wire 1 ! clk $end\n"));
+        assert!(vcd.contains("$var wire 2 \" data [1:0] $end\n$upscope $end\n"));
+        assert!(vcd.ends_with("#0\n$dumpvars\nx!\nbxx \"\n$end\n0!\n#10\n1!\nb11 \"\n#20\n"));
+    }
+
+    #[test]
+    fn expands_delta_cycles() {
+        let vcd = write(VcdOptions {
+            delta_cycles: DeltaCycles::Expand,
+        });
+        assert!(vcd.ends_with("#10\n1!\nb0x \"\n#11\nb11 \"\n#21\n"));
+    }
+
+    #[test]
+    fn expanded_delta_cycles_shift_later_time_steps() {
+        let hierarchy = hierarchy();
+        let clk = SignalElementId::new_scalar(hierarchy.root_modules[0].signals[0].id);
+        let mut vcd = VcdWriter::new(
+            vec![],
+            &hierarchy,
+            VcdOptions {
+                delta_cycles: DeltaCycles::Expand,
+            },
+        )
+        .unwrap();
+        vcd.write_events(&EventsUpdate {
+            time_range: LogicalTime::ZERO..LogicalTime::from(12),
+            signals: vec![events(
+                clk,
+                &[
+                    ((10, 0), Logic::Zero),
+                    ((10, 1), Logic::One),
+                    ((11, 0), Logic::Zero),
+                ],
+            )],
+            reports: vec![],
+        })
+        .unwrap();
+        let vcd = String::from_utf8(vcd.finish().unwrap()).unwrap();
+        assert!(vcd.ends_with("#10\n0!\n#11\n1!\n#12\n0!\n#13\n"));
+    }
+
+    #[test]
+    fn integers_start_unknown() {
+        let mut hierarchy = hierarchy();
+        hierarchy.root_modules[0].signals[0].typ = SignalType::Integer {
+            min: 0,
+            max: 9,
+            direction: Direction::To,
+        };
+        let vcd = VcdWriter::new(vec![], &hierarchy, VcdOptions::default()).unwrap();
+        let vcd = String::from_utf8(vcd.finish().unwrap()).unwrap();
+        assert!(vcd.contains("$dumpvars\nbx !\n"));
+    }
+
+    #[test]
+    fn uses_time_resolution_as_timescale() {
+        let mut hierarchy = hierarchy();
+        hierarchy.time_resolution = PhysicalTime(10);
+        let mut vcd = VcdWriter::new(vec![], &hierarchy, VcdOptions::default()).unwrap();
+        vcd.write_events(&EventsUpdate {
+            time_range: LogicalTime::ZERO..LogicalTime::from(30),
+            signals: vec![events(
+                SignalElementId::new_scalar(hierarchy.root_modules[0].signals[0].id),
+                &[((20, 0), Logic::One)],
+            )],
+            reports: vec![],
+        })
+        .unwrap();
+        let vcd = String::from_utf8(vcd.finish().unwrap()).unwrap();
+        assert!(vcd.contains("$timescale 10 fs $end"));
+        assert!(vcd.ends_with("#2\n1!\n#3\n"));
+
+        assert_eq!(
+            vcd_timescale(PhysicalTime(100_000)).as_deref(),
+            Some("100 ps")
+        );
+        assert_eq!(
+            vcd_timescale(PhysicalTime(1_000_000_000_000_000)).as_deref(),
+            Some("1 s")
+        );
+        assert_eq!(vcd_timescale(PhysicalTime(2_000)), None);
+    }
+
+    #[test]
+    fn rejects_signals_which_do_not_fit() {
+        let wide = signal("wide", 1, logic_vector(i32::MAX, 0));
+        let field = |name: &str, typ, element_offset| RecordField {
+            name: name.into(),
+            typ,
+            element_offset,
+        };
+        let inner = SignalType::Record {
+            fields: vec![field("value", SignalType::Logic, 1)],
+            element_count: 2,
+        };
+        let overflowing = SignalType::Record {
+            fields: vec![field("inner", inner, u32::MAX)],
+            element_count: u32::MAX,
+        };
+        let overflowing = signal("overflowing", 2, overflowing);
+        for signal in [wide, overflowing] {
+            let hierarchy = crate::test_utils::hierarchy(vec![module("tb", vec![], vec![signal])]);
+            let error = VcdWriter::new(vec![], &hierarchy, VcdOptions::default()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}